@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum DensityFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct DensityCell {
+    chunk_x: i64,
+    /// Cell index along tile `y`; map tooling calls the north-south axis z.
+    chunk_z: i64,
+    walkable: i64,
+    blocked: i64,
+}
+
+/// Per-cell counts for one plane. SQLite's `/` truncates toward zero, which would fold the cells
+/// either side of 0 together, so the cell index is floored explicitly: `x - ((x % g) + g) % g` is
+/// the cell's lower edge and divides exactly.
+fn density_cells(conn: &Connection, plane: i32, grid: i32) -> Result<Vec<DensityCell>> {
+    let mut stmt = conn.prepare(
+        "SELECT (x - ((x % ?1) + ?1) % ?1) / ?1 AS cx, (y - ((y % ?1) + ?1) % ?1) / ?1 AS cz, \
+                SUM(CASE WHEN COALESCE(walk_mask, 0) != 0 THEN 1 ELSE 0 END), \
+                SUM(CASE WHEN COALESCE(walk_mask, 0) = 0 THEN 1 ELSE 0 END) \
+         FROM tiles WHERE plane = ?2 GROUP BY cx, cz ORDER BY cx, cz",
+    )?;
+    let cells = stmt
+        .query_map(params![grid, plane], |r| {
+            Ok(DensityCell { chunk_x: r.get(0)?, chunk_z: r.get(1)?, walkable: r.get(2)?, blocked: r.get(3)? })
+        })?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(cells)
+}
+
+/// Buckets the tiles of one plane into `grid` x `grid` cells and writes walkable/blocked counts per
+/// cell. A tile counts as blocked when its walk_mask is 0 or NULL, so the two columns always sum to
+/// the number of tile rows in the cell.
+pub fn cmd_density(db_path: &Path, plane: i32, grid: i32, format: DensityFormat, out: Option<&Path>) -> Result<()> {
+    if grid <= 0 {
+        bail!("--grid must be positive, got {}", grid);
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open DB at {}", db_path.display()))?;
+    let cells = density_cells(&conn, plane, grid)?;
+
+    let mut w: Box<dyn Write> = match out {
+        Some(p) => Box::new(BufWriter::new(
+            File::create(p).with_context(|| format!("Failed to create {}", p.display()))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    match format {
+        DensityFormat::Csv => {
+            writeln!(w, "chunk_x,chunk_z,walkable,blocked")?;
+            for c in &cells {
+                writeln!(w, "{},{},{},{}", c.chunk_x, c.chunk_z, c.walkable, c.blocked)?;
+            }
+        }
+        DensityFormat::Json => {
+            serde_json::to_writer_pretty(&mut w, &cells)?;
+            writeln!(w)?;
+        }
+    }
+    w.flush()?;
+
+    if let Some(p) = out {
+        let walkable: i64 = cells.iter().map(|c| c.walkable).sum();
+        let blocked: i64 = cells.iter().map(|c| c.blocked).sum();
+        println!(
+            "Wrote {} cells for plane {} ({} walkable, {} blocked tiles) to {}",
+            cells.len(),
+            plane,
+            walkable,
+            blocked,
+            p.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch, tiles_db, OPEN};
+
+    fn summary(cells: &[DensityCell]) -> Vec<(i64, i64, i64, i64)> {
+        cells.iter().map(|c| (c.chunk_x, c.chunk_z, c.walkable, c.blocked)).collect()
+    }
+
+    #[test]
+    fn cells_floor_negative_coordinates() {
+        let (_dir, path) = scratch("density.db");
+        let conn = tiles_db(
+            &path,
+            &[
+                (-1, -1, 0, Some(OPEN)),
+                (-4, 0, 0, None),
+                (-5, 3, 0, Some(OPEN)),
+                (0, 0, 0, Some(OPEN)),
+                (3, 3, 0, Some(0)),
+                (4, 0, 0, Some(OPEN)),
+            ],
+        );
+        assert_eq!(
+            summary(&density_cells(&conn, 0, 4).unwrap()),
+            vec![(-2, 0, 1, 0), (-1, -1, 1, 0), (-1, 0, 0, 1), (0, 0, 1, 1), (1, 0, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn outputs_use_chunk_x_and_chunk_z() {
+        let (dir, path) = scratch("density.db");
+        tiles_db(&path, &[(1, 70, 0, Some(OPEN)), (2, 71, 0, None)]);
+
+        let csv = dir.path().join("density.csv");
+        cmd_density(&path, 0, 64, DensityFormat::Csv, Some(&csv)).unwrap();
+        assert_eq!(std::fs::read_to_string(&csv).unwrap(), "chunk_x,chunk_z,walkable,blocked\n0,1,1,1\n");
+
+        let json = dir.path().join("density.json");
+        cmd_density(&path, 0, 64, DensityFormat::Json, Some(&json)).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(v, serde_json::json!([{"chunk_x": 0, "chunk_z": 1, "walkable": 1, "blocked": 1}]));
+    }
+
+    #[test]
+    fn cells_only_count_the_requested_plane() {
+        let (_dir, path) = scratch("density.db");
+        let conn = tiles_db(&path, &[(1, 1, 0, Some(OPEN)), (2, 2, 0, Some(0)), (1, 1, 1, Some(OPEN))]);
+        assert_eq!(summary(&density_cells(&conn, 0, 8).unwrap()), vec![(0, 0, 1, 1)]);
+        assert_eq!(summary(&density_cells(&conn, 1, 8).unwrap()), vec![(0, 0, 1, 0)]);
+        assert!(density_cells(&conn, 2, 8).unwrap().is_empty());
+    }
+}
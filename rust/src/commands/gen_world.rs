@@ -0,0 +1,274 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+// walk_mask bits (0..7): left, bottom, right, top, topleft, bottomleft, bottomright, topright
+const DIRS: [(i32, i32); 8] = [(-1, 0), (0, -1), (1, 0), (0, 1), (-1, 1), (-1, -1), (1, -1), (1, 1)];
+
+/// SplitMix64: tiny, seedable and good enough for reproducible synthetic data.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+pub struct GenWorldOptions {
+    pub width: i32,
+    pub height: i32,
+    pub planes: i32,
+    pub density: f64,
+    pub seed: u64,
+    pub teleports: usize,
+    pub origin_x: i32,
+    pub origin_y: i32,
+}
+
+/// Fills a fresh DB with a procedurally generated world: every cell of the `width` x `height` x
+/// `planes` box gets a tile row, walkable with probability `density`, with walk masks linking
+/// walkable neighbours (diagonals only when both cardinals are open). Random doors between planes
+/// and lodestones are added on walkable tiles. A tile counts as walkable when its walk mask is
+/// non-zero, so isolated open cells are blocked. The origin tile is always walkable (unless the
+/// world is a single tile), with an open neighbour to the east or, in a one-column world, north.
+pub fn cmd_gen_world(db_path: &Path, opts: &GenWorldOptions) -> Result<()> {
+    if opts.width <= 0 || opts.height <= 0 || opts.planes <= 0 {
+        bail!("--width, --height and --planes must be positive");
+    }
+    if !(0.0..=1.0).contains(&opts.density) {
+        bail!("--density must be between 0 and 1, got {}", opts.density);
+    }
+
+    println!(
+        "Generating {}x{}x{} world (density {}, seed {}) into {}",
+        opts.width, opts.height, opts.planes, opts.density, opts.seed, db_path.display()
+    );
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("Failed to open DB at {}", db_path.display()))?;
+    crate::db::create_tables(&conn)?;
+
+    let mut rng = Rng(opts.seed);
+    let (w, h) = (opts.width as usize, opts.height as usize);
+    let idx = |x: usize, y: usize, p: usize| (p * h + y) * w + x;
+    let mut walkable = vec![false; w * h * opts.planes as usize];
+    for cell in walkable.iter_mut() {
+        *cell = rng.next_f64() < opts.density;
+    }
+    walkable[idx(0, 0, 0)] = true;
+    if w > 1 {
+        walkable[idx(1, 0, 0)] = true;
+    } else if h > 1 {
+        walkable[idx(0, 1, 0)] = true;
+    }
+
+    let is_open = |x: i32, y: i32, p: usize| -> bool {
+        x >= 0 && y >= 0 && x < opts.width && y < opts.height && walkable[idx(x as usize, y as usize, p)]
+    };
+
+    let tx = conn.transaction()?;
+    let mut open_tiles: Vec<(i32, i32, i32)> = Vec::new();
+    // Walkable (non-zero mask) tiles per plane, so picking a door destination doesn't rescan every tile.
+    let mut open_by_plane: Vec<Vec<(i32, i32, i32)>> = vec![Vec::new(); opts.planes as usize];
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO tiles (x, y, plane, walk_mask, RegionID) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (p, plane_open) in open_by_plane.iter_mut().enumerate() {
+            for y in 0..opts.height {
+                for x in 0..opts.width {
+                    let mut mask: i64 = 0;
+                    if is_open(x, y, p) {
+                        for (bit, (dx, dy)) in DIRS.iter().enumerate() {
+                            let diagonal_clear = *dx == 0
+                                || *dy == 0
+                                || (is_open(x + dx, y, p) && is_open(x, y + dy, p));
+                            if diagonal_clear && is_open(x + dx, y + dy, p) {
+                                mask |= 1 << bit;
+                            }
+                        }
+                    }
+                    let wx = (opts.origin_x + x) as i64;
+                    let wy = (opts.origin_y + y) as i64;
+                    let region_id = ((wx >> 6) << 8) + (wy >> 6);
+                    stmt.execute(params![wx, wy, p as i64, mask, region_id])?;
+                    if mask != 0 {
+                        let t = (opts.origin_x + x, opts.origin_y + y, p as i32);
+                        open_tiles.push(t);
+                        plane_open.push(t);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut doors = 0usize;
+    let mut lodestones = 0usize;
+    if !open_tiles.is_empty() {
+        for i in 0..opts.teleports {
+            let a = open_tiles[rng.below(open_tiles.len())];
+            if i.is_multiple_of(2) && opts.planes > 1 {
+                // Door to a random walkable tile one plane up (wrapping to plane 0).
+                let dst_plane = (a.2 + 1) % opts.planes;
+                let candidates = &open_by_plane[dst_plane as usize];
+                if candidates.is_empty() {
+                    continue;
+                }
+                let b = candidates[rng.below(candidates.len())];
+                tx.execute(
+                    "INSERT INTO teleports_door_nodes (direction, tile_outside_x, tile_outside_y, tile_outside_plane, \
+                     tile_inside_x, tile_inside_y, tile_inside_plane, cost) VALUES ('IN', ?1, ?2, ?3, ?4, ?5, ?6, 1)",
+                    params![a.0, a.1, a.2, b.0, b.1, b.2],
+                )?;
+                doors += 1;
+            } else {
+                tx.execute(
+                    "INSERT INTO teleports_lodestone_nodes (lodestone, dest_x, dest_y, dest_plane, cost) \
+                     VALUES (?1, ?2, ?3, ?4, 10)",
+                    params![format!("synthetic_{}", i), a.0, a.1, a.2],
+                )?;
+                lodestones += 1;
+            }
+        }
+    }
+    tx.commit()?;
+
+    let total = walkable.len();
+    println!(
+        "Generated {} tiles ({} walkable, {} blocked), {} doors, {} lodestones",
+        total,
+        open_tiles.len(),
+        total - open_tiles.len(),
+        doors,
+        lodestones
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch;
+
+    fn opts(seed: u64) -> GenWorldOptions {
+        GenWorldOptions {
+            width: 128,
+            height: 128,
+            planes: 2,
+            density: 0.7,
+            seed,
+            teleports: 50,
+            origin_x: 3200,
+            origin_y: 3200,
+        }
+    }
+
+    /// Every generated row, in a stable order, as one string per table.
+    fn dump(path: &Path) -> Vec<String> {
+        let conn = Connection::open(path).unwrap();
+        [
+            "SELECT x, y, plane, walk_mask, RegionID FROM tiles ORDER BY plane, y, x",
+            "SELECT tile_outside_x, tile_outside_y, tile_outside_plane, tile_inside_x, tile_inside_y, tile_inside_plane \
+             FROM teleports_door_nodes ORDER BY id",
+            "SELECT lodestone, dest_x, dest_y, dest_plane FROM teleports_lodestone_nodes ORDER BY id",
+        ]
+        .iter()
+        .map(|sql| {
+            let mut stmt = conn.prepare(sql).unwrap();
+            let n = stmt.column_count();
+            let rows: Vec<String> = stmt
+                .query_map([], |r| {
+                    (0..n).map(|i| r.get::<_, rusqlite::types::Value>(i).map(|v| format!("{:?}", v))).collect()
+                })
+                .unwrap()
+                .map(|r: rusqlite::Result<Vec<String>>| r.unwrap().join(","))
+                .collect();
+            rows.join("\n")
+        })
+        .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_world() {
+        let (_a_dir, a) = scratch("a.db");
+        let (_b_dir, b) = scratch("b.db");
+        let (_c_dir, c) = scratch("c.db");
+        cmd_gen_world(&a, &opts(42)).unwrap();
+        cmd_gen_world(&b, &opts(42)).unwrap();
+        cmd_gen_world(&c, &opts(43)).unwrap();
+
+        let (a, b, c) = (dump(&a), dump(&b), dump(&c));
+        assert_eq!(a, b);
+        assert_ne!(a[0], c[0]);
+        assert_eq!(a[0].lines().count(), 128 * 128 * 2);
+        assert_eq!(a[1].lines().count() + a[2].lines().count(), 50);
+    }
+
+    #[test]
+    fn doors_lead_one_plane_up_onto_walkable_tiles() {
+        for seed in 0..8 {
+            let (_dir, path) = scratch("world.db");
+            cmd_gen_world(&path, &GenWorldOptions { width: 64, height: 64, ..opts(seed) }).unwrap();
+            check_doors_and_origin(&Connection::open(&path).unwrap());
+        }
+    }
+
+    fn check_doors_and_origin(conn: &Connection) {
+        let doors: i64 = conn.query_row("SELECT COUNT(*) FROM teleports_door_nodes", [], |r| r.get(0)).unwrap();
+        assert_eq!(doors, 25);
+        let bad: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM teleports_door_nodes d \
+                 LEFT JOIN tiles t ON t.x = d.tile_inside_x AND t.y = d.tile_inside_y AND t.plane = d.tile_inside_plane \
+                 WHERE d.tile_inside_plane != (d.tile_outside_plane + 1) % 2 OR COALESCE(t.walk_mask, 0) = 0",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(bad, 0);
+        let origin: i64 =
+            conn.query_row("SELECT walk_mask FROM tiles WHERE x = 3200 AND y = 3200 AND plane = 0", [], |r| r.get(0)).unwrap();
+        assert_ne!(origin & 0b100, 0, "origin must open east, mask {origin:#x}");
+    }
+
+    #[test]
+    fn isolated_cells_are_blocked_and_the_origin_is_connected() {
+        // At density 0.05 almost every open cell has no open neighbour.
+        let (_dir, path) = scratch("sparse.db");
+        cmd_gen_world(&path, &GenWorldOptions { density: 0.05, teleports: 0, ..opts(3) }).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        let origin: i64 =
+            conn.query_row("SELECT walk_mask FROM tiles WHERE x = 3200 AND y = 3200 AND plane = 0", [], |r| r.get(0)).unwrap();
+        assert_ne!(origin & 0b100, 0);
+        let east: i64 =
+            conn.query_row("SELECT walk_mask FROM tiles WHERE x = 3201 AND y = 3200 AND plane = 0", [], |r| r.get(0)).unwrap();
+        assert_ne!(east & 0b001, 0);
+    }
+
+    #[test]
+    fn walkable_fraction_tracks_density() {
+        for density in [0.3, 0.7, 0.95] {
+            let (_dir, path) = scratch("world.db");
+            cmd_gen_world(&path, &GenWorldOptions { density, planes: 1, teleports: 0, ..opts(11) }).unwrap();
+            let conn = Connection::open(&path).unwrap();
+            let walkable: f64 = conn
+                .query_row("SELECT AVG(walk_mask != 0) FROM tiles", [], |r| r.get(0))
+                .unwrap();
+            // Open cells with no open cardinal neighbour are blocked, roughly density * (1 - density)^4
+            // of all cells, so allow for that on top of sampling noise.
+            let expected = density * (1.0 - (1.0 - density).powi(4));
+            assert!((walkable - expected).abs() < 0.02, "density {density}: walkable {walkable}, expected ~{expected}");
+        }
+    }
+}
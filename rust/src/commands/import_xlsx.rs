@@ -4,7 +4,8 @@ use rusqlite::{params_from_iter, Connection};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tempfile::NamedTempFile;
+use std::time::{Duration, SystemTime};
+use tempfile::{NamedTempFile, TempPath};
 use std::io::Write;
 use url::Url;
 
@@ -113,21 +114,87 @@ struct Table {
     columns: HashMap<String, Column>, // lowercased key -> Column
 }
 
-pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String], sheets: &[String]) -> Result<()> {
+pub struct ImportOptions<'a> {
+    pub dry_run: bool,
+    pub truncate: &'a [String],
+    pub sheets: &'a [String],
+    pub validate_doors: bool,
+    pub zip_entry: Option<&'a str>,
+    pub download_retries: u32,
+    /// Only report how each sheet's headers map onto DB columns; import nothing.
+    pub check_only: bool,
+    /// Added to every `*plane` column of teleport node rows.
+    pub plane_offset: i64,
+}
+
+/// How one worksheet lines up with the DB, as reported by `--check-only`.
+#[derive(Debug)]
+pub struct SheetCheck {
+    pub sheet: String,
+    /// Matching table name; `None` when the DB has no table named like the sheet.
+    pub table: Option<String>,
+    /// (header, DB column) pairs that would be imported.
+    pub mapped: Vec<(String, String)>,
+    /// Headers with no matching column; their cells are ignored on import.
+    pub unmapped: Vec<String>,
+}
+
+pub fn cmd_import_xlsx(xlsx: &str, db: &Path, opts: &ImportOptions) -> Result<()> {
+    let ImportOptions {
+        dry_run,
+        truncate,
+        sheets,
+        validate_doors,
+        zip_entry,
+        download_retries,
+        check_only,
+        plane_offset,
+    } = *opts;
     if !db.exists() {
         bail!("SQLite DB not found: {}", db.display());
     }
 
-    // Obtain local XLSX path, downloading if Google Sheets URL.
-    let (xlsx_path, cleanup_temp): (PathBuf, bool) = if is_google_sheets_url(xlsx) {
-        println!("Downloading Google Sheet as .xlsx ...");
-        (download_google_sheet_as_xlsx(xlsx)?, true)
+    // Obtain local XLSX path, downloading if Google Sheets URL and extracting if a .zip archive.
+    // Every temp file created along the way is removed when its guard drops, on error paths too.
+    let mut temp_files: Vec<TempPath> = Vec::new();
+    let mut csv_sheet: Option<(String, calamine::Range<DataType>)> = None;
+    let fetched = if is_google_sheets_url(xlsx) {
+        Some(download_google_sheet(xlsx, GSHEETS_EXPORT_BASE, sheets, download_retries)?)
+    } else if is_zip_source(xlsx) {
+        let zip_path = if is_http_url(xlsx) {
+            println!("Downloading zip archive {} ...", xlsx);
+            let tmp = download_to_temp(xlsx, download_retries)?;
+            let p = tmp.to_path_buf();
+            temp_files.push(tmp);
+            p
+        } else {
+            let p = PathBuf::from(xlsx);
+            if !p.exists() {
+                bail!("Zip archive not found: {}", p.display());
+            }
+            p
+        };
+        Some(extract_from_zip(&zip_path, zip_entry)?)
     } else {
-        let p = PathBuf::from(xlsx);
-        if !p.exists() {
-            bail!("XLSX file not found: {}", p.display());
+        None
+    };
+    let xlsx_path: PathBuf = match fetched {
+        Some(Spreadsheet::Workbook(tmp)) => {
+            let p = tmp.to_path_buf();
+            temp_files.push(tmp);
+            p
+        }
+        Some(Spreadsheet::Csv(table, range)) => {
+            csv_sheet = Some((table, range));
+            PathBuf::new()
+        }
+        None => {
+            let p = PathBuf::from(xlsx);
+            if !p.exists() {
+                bail!("XLSX file not found: {}", p.display());
+            }
+            p
         }
-        (p, false)
     };
 
     let mut conn = Connection::open(db).with_context(|| format!("Open DB {}", db.display()))?;
@@ -135,6 +202,30 @@ pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String]
 
     // Introspect tables outside the transaction for simple typing
     let tables = fetch_existing_tables(&conn)?;
+
+    // Open workbook (or use the CSV fallback's single sheet)
+    let mut wb = match csv_sheet {
+        Some(_) => None,
+        None => Some(
+            open_workbook_auto(&xlsx_path)
+                .with_context(|| format!("Open workbook {}", xlsx_path.display()))?,
+        ),
+    };
+
+    // Sheet order: process 'requirements' first
+    let mut sheet_names: Vec<String> = match (&wb, &csv_sheet) {
+        (Some(wb), _) => wb.sheet_names().to_vec(),
+        (None, Some((name, _))) => vec![name.clone()],
+        (None, None) => Vec::new(),
+    };
+    sheet_names.sort_by_key(|n| if n.eq_ignore_ascii_case("requirements") { 0 } else { 1 });
+
+    if check_only {
+        let report = check_sheets(&mut wb, &mut csv_sheet, &sheet_names, &tables)?;
+        print_check_report(&report);
+        return Ok(());
+    }
+
     let mut tx = conn.transaction()?;
     let truncate_set: HashSet<String> = truncate.iter().map(|s| s.to_lowercase()).collect();
     let only_set: Option<HashSet<String>> = if sheets.is_empty() {
@@ -170,18 +261,6 @@ pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String]
         }
     }
 
-    // Open workbook
-    let mut wb = open_workbook_auto(&xlsx_path)
-        .with_context(|| format!("Open workbook {}", xlsx_path.display()))?;
-
-    // Sheet order: process 'requirements' first
-    let mut sheet_names: Vec<String> = wb
-        .sheet_names()
-        .into_iter()
-        .cloned()
-        .collect();
-    sheet_names.sort_by_key(|n| if n.eq_ignore_ascii_case("requirements") { 0 } else { 1 });
-
     let mut total_inserted: usize = 0;
     for sheet in sheet_names {
         let sheet_key = sheet.to_lowercase();
@@ -195,15 +274,22 @@ pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String]
             continue;
         };
 
-        if let Some(Ok(range)) = wb.worksheet_range(&sheet) {
+        let range = match (&mut wb, &mut csv_sheet) {
+            (Some(wb), _) => Some(read_range(wb, &sheet)?),
+            (None, Some((_, range))) => Some(std::mem::take(range)),
+            (None, None) => None,
+        };
+        if let Some(range) = range {
             println!("Processing worksheet '{}' -> table '{}'", sheet, table.name);
             let rows = read_worksheet(&range, table)?;
             println!("  Prepared {} row(s)", rows.len());
             let mut sheet_preview = 0usize;
-            if TELEPORT_NODE_TABLES.contains(&table.name.to_ascii_lowercase().as_str()) {
-                teleports_touched = true;
-            }
+            let is_teleport_table = TELEPORT_NODE_TABLES.contains(&table.name.to_ascii_lowercase().as_str());
+            teleports_touched |= is_teleport_table;
             for mut r in rows {
+                if is_teleport_table && plane_offset != 0 {
+                    shift_planes(&mut r, plane_offset);
+                }
                 normalize_specials(&table.name, &mut r)?;
                 validate_specials(&table.name, &r)?;
                 let (sql, params) = build_insert_sql(table, &r)?;
@@ -249,6 +335,15 @@ pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String]
     //     }
     // }
 
+    if validate_doors && tables.contains_key("teleports_door_nodes") {
+        let bad = validate_door_nodes(&tx)?;
+        if bad.is_empty() {
+            println!("Door validation: no issues found");
+        } else {
+            println!("Door validation: {} suspicious door row(s): {:?}", bad.len(), bad);
+        }
+    }
+
     if dry_run {
         println!("Dry-run complete. Rows that would be inserted: {}", total_inserted);
         // Drop transaction without commit -> rollback
@@ -257,12 +352,140 @@ pub fn cmd_import_xlsx(xlsx: &str, db: &Path, dry_run: bool, truncate: &[String]
         println!("Import complete. Rows inserted: {}", total_inserted);
     }
 
-    // Cleanup temp file if downloaded
-    if cleanup_temp {
-        let _ = fs::remove_file(&xlsx_path);
+    Ok(())
+}
+
+/// Adds `offset` to every integer `plane`/`*_plane` value of a row (e.g. `dest_plane`, `tile_inside_plane`).
+fn shift_planes(row: &mut BTreeMap<String, rusqlite::types::Value>, offset: i64) {
+    for (col, v) in row.iter_mut() {
+        let col = col.to_ascii_lowercase();
+        if col != "plane" && !col.ends_with("_plane") {
+            continue;
+        }
+        if let rusqlite::types::Value::Integer(p) = v {
+            *p += offset;
+        }
     }
+}
 
-    Ok(())
+/// Reads a worksheet, failing (rather than skipping it) when it is missing or unreadable.
+fn read_range(wb: &mut calamine::Sheets<std::io::BufReader<fs::File>>, sheet: &str) -> Result<calamine::Range<DataType>> {
+    wb.worksheet_range(sheet)
+        .with_context(|| format!("Worksheet '{}' not found in workbook", sheet))?
+        .with_context(|| format!("Read worksheet '{}'", sheet))
+}
+
+/// The `--check-only` report for a local workbook: how each sheet's headers map onto `db`'s tables.
+/// Nothing is written to the DB.
+pub fn check_workbook(xlsx_path: &Path, db: &Path) -> Result<Vec<SheetCheck>> {
+    let conn = Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Open DB {}", db.display()))?;
+    let tables = fetch_existing_tables(&conn)?;
+    let mut wb = Some(open_workbook_auto(xlsx_path).with_context(|| format!("Open workbook {}", xlsx_path.display()))?);
+    let sheet_names = wb.as_ref().map(|wb| wb.sheet_names().to_vec()).unwrap_or_default();
+    check_sheets(&mut wb, &mut None, &sheet_names, &tables)
+}
+
+/// Maps every sheet's header row onto the DB schema, using the same header matching as the import.
+/// calamine 0.21 has no header-only reader, so each sheet with a matching table is loaded in full.
+fn check_sheets(
+    wb: &mut Option<calamine::Sheets<std::io::BufReader<fs::File>>>,
+    csv_sheet: &mut Option<(String, calamine::Range<DataType>)>,
+    sheet_names: &[String],
+    tables: &HashMap<String, Table>,
+) -> Result<Vec<SheetCheck>> {
+    let mut report = Vec::new();
+    for sheet in sheet_names {
+        let Some(table) = tables.get(&sheet.to_lowercase()) else {
+            report.push(SheetCheck { sheet: sheet.clone(), table: None, mapped: Vec::new(), unmapped: Vec::new() });
+            continue;
+        };
+        let range = match (&mut *wb, &mut *csv_sheet) {
+            (Some(wb), _) => Some(read_range(wb, sheet)?),
+            (None, Some((_, range))) => Some(std::mem::take(range)),
+            (None, None) => None,
+        };
+        let mut check = SheetCheck { sheet: sheet.clone(), table: Some(table.name.clone()), mapped: Vec::new(), unmapped: Vec::new() };
+        let headers = range.as_ref().and_then(|r| r.rows().next()).unwrap_or(&[]);
+        for h in headers.iter().filter_map(normalize_header) {
+            match table.columns.get(&h.to_lowercase()) {
+                Some(c) => check.mapped.push((h, c.name.clone())),
+                None => check.unmapped.push(h),
+            }
+        }
+        report.push(check);
+    }
+    Ok(report)
+}
+
+fn print_check_report(report: &[SheetCheck]) {
+    for c in report {
+        let Some(table) = &c.table else {
+            println!("Sheet '{}': NO MATCHING TABLE (would be skipped)", c.sheet);
+            continue;
+        };
+        println!("Sheet '{}' -> table '{}'", c.sheet, table);
+        for (h, col) in &c.mapped {
+            if h == col {
+                println!("  {}", h);
+            } else {
+                println!("  {} -> {}", h, col);
+            }
+        }
+        for h in &c.unmapped {
+            println!("  {} (ignored: no such column)", h);
+        }
+    }
+    let unmatched = report.iter().filter(|c| c.table.is_none()).count();
+    let ignored: usize = report.iter().map(|c| c.unmapped.len()).sum();
+    println!(
+        "Check complete: {} sheet(s), {} without a table, {} ignored header(s)",
+        report.len(),
+        unmatched,
+        ignored
+    );
+}
+
+/// Flags door rows whose inside/outside tiles are not adjacent (same plane, at most one tile apart)
+/// or that have neither `real_id_open` nor `real_id_closed`. Returns the offending door ids.
+fn validate_door_nodes(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, tile_inside_x, tile_inside_y, tile_inside_plane, \
+                tile_outside_x, tile_outside_y, tile_outside_plane, \
+                real_id_open, real_id_closed \
+         FROM teleports_door_nodes ORDER BY id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut bad = Vec::new();
+    while let Some(r) = rows.next()? {
+        let id: i64 = r.get(0)?;
+        let inside: (Option<i64>, Option<i64>, Option<i64>) = (r.get(1)?, r.get(2)?, r.get(3)?);
+        let outside: (Option<i64>, Option<i64>, Option<i64>) = (r.get(4)?, r.get(5)?, r.get(6)?);
+        let real_open: Option<i64> = r.get(7)?;
+        let real_closed: Option<i64> = r.get(8)?;
+
+        let adjacent = match (inside, outside) {
+            ((Some(ix), Some(iy), Some(ip)), (Some(ox), Some(oy), Some(op))) => {
+                ip == op && (ix - ox).abs() <= 1 && (iy - oy).abs() <= 1
+            }
+            _ => false,
+        };
+        let has_real_id = real_open.is_some() || real_closed.is_some();
+
+        if !adjacent {
+            println!(
+                "  door {}: inside {:?} and outside {:?} are not adjacent",
+                id, inside, outside
+            );
+        }
+        if !has_real_id {
+            println!("  door {}: both real_id_open and real_id_closed are NULL", id);
+        }
+        if !adjacent || !has_real_id {
+            bad.push(id);
+        }
+    }
+    Ok(bad)
 }
 
 fn is_google_sheets_url(s: &str) -> bool {
@@ -275,7 +498,147 @@ fn is_google_sheets_url(s: &str) -> bool {
     }
 }
 
-fn build_gsheet_export_url(doc_url: &str) -> Result<String> {
+fn is_http_url(s: &str) -> bool {
+    Url::parse(s)
+        .map(|u| u.scheme() == "http" || u.scheme() == "https")
+        .unwrap_or(false)
+}
+
+fn is_zip_source(s: &str) -> bool {
+    let path = match Url::parse(s) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url.path().to_string(),
+        _ => s.to_string(),
+    };
+    path.to_ascii_lowercase().ends_with(".zip")
+}
+
+/// Extracts the spreadsheet to import from a zip archive into a temp file, deleted when the
+/// returned guard drops. With several .xlsx entries present, `entry_name` must pick one.
+/// Picks the spreadsheet in a zip archive: `entry_name` when given, otherwise the only `.xlsx` or
+/// `.csv` entry. A workbook is extracted to a temp file; a CSV entry is parsed in memory as the
+/// sheet for the table named by its file stem (`data/teleports_npc_nodes.csv` -> `teleports_npc_nodes`).
+fn extract_from_zip(zip_path: &Path, entry_name: Option<&str>) -> Result<Spreadsheet> {
+    let file = fs::File::open(zip_path).with_context(|| format!("Open zip {}", zip_path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Read zip archive {}", zip_path.display()))?;
+
+    let candidates: Vec<String> = archive
+        .file_names()
+        .filter(|n| {
+            let n = n.to_ascii_lowercase();
+            n.ends_with(".xlsx") || n.ends_with(".csv")
+        })
+        .map(|n| n.to_string())
+        .collect();
+
+    let chosen = match entry_name {
+        Some(name) => {
+            if archive.file_names().all(|n| n != name) {
+                bail!("--zip-entry '{}' not found in {} (spreadsheets: {:?})", name, zip_path.display(), candidates);
+            }
+            name.to_string()
+        }
+        None => match candidates.len() {
+            0 => bail!("No .xlsx or .csv entry found in {}", zip_path.display()),
+            1 => candidates[0].clone(),
+            _ => bail!(
+                "Multiple spreadsheets in {}; choose one with --zip-entry: {:?}",
+                zip_path.display(),
+                candidates
+            ),
+        },
+    };
+
+    println!("Extracting '{}' from {} ...", chosen, zip_path.display());
+    let mut entry = archive.by_name(&chosen)?;
+    let entry_path = Path::new(&chosen);
+    let ext = entry_path.extension().and_then(|e| e.to_str()).unwrap_or("xlsx").to_ascii_lowercase();
+    if ext == "csv" {
+        let table = entry_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut text)
+            .with_context(|| format!("Read '{}' from {} as UTF-8", chosen, zip_path.display()))?;
+        return Ok(Spreadsheet::Csv(table, parse_csv(&text)?));
+    }
+    // Keep the entry's extension so calamine can tell .xlsx from .xls/.ods.
+    let mut tmp = tempfile::Builder::new().suffix(&format!(".{}", ext)).tempfile()?;
+    std::io::copy(&mut entry, tmp.as_file_mut())?;
+    Ok(Spreadsheet::Workbook(tmp.into_temp_path()))
+}
+
+/// Longest wait between download attempts. A `Retry-After` beyond this is cut short (and the cut
+/// logged) so a misconfigured server can't stall an import for hours.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+/// Parses a `Retry-After` value, either delta-seconds or an HTTP-date (RFC 9110 section 10.2.3).
+/// A date in the past means "retry now".
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// GETs `url`, retrying connection errors, body read errors, 429s and 5xx responses up to `retries`
+/// times. Waits for the server's `Retry-After` when given (capped at `MAX_RETRY_DELAY`), otherwise
+/// backs off exponentially from 1s to 64s.
+fn fetch_with_retries(url: &str, retries: u32) -> Result<Vec<u8>> {
+    let mut attempt: u32 = 0;
+    loop {
+        let (reason, retry_after) = match reqwest::blocking::get(url) {
+            Ok(resp) if resp.status().is_success() => match resp.bytes() {
+                Ok(body) => return Ok(body.to_vec()),
+                Err(e) => (format!("reading body: {}", e), None),
+            },
+            Ok(resp) => {
+                let status = resp.status();
+                if !(status.as_u16() == 429 || status.is_server_error()) {
+                    bail!("Failed to download {}: HTTP {}", url, status);
+                }
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| parse_retry_after(v, SystemTime::now()));
+                (format!("HTTP {}", status), retry_after)
+            }
+            Err(e) => (e.to_string(), None),
+        };
+
+        if attempt >= retries {
+            bail!("Failed to download {} after {} attempt(s); last error: {}", url, attempt + 1, reason);
+        }
+        let mut delay = retry_after.unwrap_or_else(|| Duration::from_secs(1 << attempt.min(6)));
+        if delay > MAX_RETRY_DELAY {
+            println!("Server asked to wait {}s; capping at {}s", delay.as_secs(), MAX_RETRY_DELAY.as_secs());
+            delay = MAX_RETRY_DELAY;
+        }
+        println!(
+            "Download of {} failed ({}); retrying in {}s ({}/{})",
+            url,
+            reason,
+            delay.as_secs(),
+            attempt + 1,
+            retries
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+fn download_to_temp(url: &str, retries: u32) -> Result<TempPath> {
+    let bytes = fetch_with_retries(url, retries)?;
+    let mut tmp = NamedTempFile::new()?;
+    tmp.as_file_mut().write_all(&bytes)?;
+    Ok(tmp.into_temp_path())
+}
+
+/// Scheme and host the Google Sheets exports are fetched from; tests substitute a local server.
+const GSHEETS_EXPORT_BASE: &str = "https://docs.google.com";
+
+fn build_gsheet_export_url(doc_url: &str, export_base: &str, format: &str) -> Result<String> {
     let url = Url::parse(doc_url)?;
     // Typical path: /spreadsheets/d/<sheet_id>/edit
     let parts: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
@@ -289,34 +652,92 @@ fn build_gsheet_export_url(doc_url: &str) -> Result<String> {
     let Some(sheet_id) = sheet_id else { bail!("Unable to parse Google Sheets ID from URL"); };
 
     let mut base = format!(
-        "https://docs.google.com/spreadsheets/d/{}/export?format=xlsx",
-        sheet_id
+        "{}/spreadsheets/d/{}/export?format={}",
+        export_base, sheet_id, format
     );
-    if let Some(q) = url.query() {
-        for pair in q.split('&') {
-            if let Some((k, v)) = pair.split_once('=') {
-                if k == "gid" {
-                    base.push_str("&gid=");
-                    base.push_str(v);
-                }
-            }
-        }
+    // Browser links carry the tab as `#gid=`, shared links as `?gid=`.
+    let gid = [url.query(), url.fragment()]
+        .into_iter()
+        .flatten()
+        .flat_map(|q| q.split('&'))
+        .find_map(|pair| pair.strip_prefix("gid="));
+    if let Some(gid) = gid {
+        base.push_str("&gid=");
+        base.push_str(gid);
     }
     Ok(base)
 }
 
-fn download_google_sheet_as_xlsx(doc_url: &str) -> Result<PathBuf> {
-    let export = build_gsheet_export_url(doc_url)?;
-    let resp = reqwest::blocking::get(&export)
-        .with_context(|| format!("Download {}", export))?;
-    if !resp.status().is_success() {
-        bail!("Failed to download Google Sheet: HTTP {}", resp.status());
+/// A fetched spreadsheet: a workbook in a temp file, or a single CSV sheet for the named table.
+enum Spreadsheet {
+    Workbook(TempPath),
+    Csv(String, calamine::Range<DataType>),
+}
+
+/// Downloads a Google Sheet as .xlsx. The xlsx export sometimes serves an HTML error page for big
+/// workbooks; then the single-tab CSV export is used instead, which carries no sheet name, so it
+/// needs exactly one `--sheets` entry naming the table.
+fn download_google_sheet(doc_url: &str, export_base: &str, sheets: &[String], retries: u32) -> Result<Spreadsheet> {
+    println!("Downloading Google Sheet as .xlsx ...");
+    if let Some(tmp) = download_google_sheet_as_xlsx(doc_url, export_base, retries)? {
+        return Ok(Spreadsheet::Workbook(tmp));
+    }
+    let [table] = sheets else {
+        bail!(
+            "Google Sheets .xlsx export did not return a spreadsheet; to fall back to the CSV export \
+             put the tab's gid in the URL and name its table with a single --sheets entry"
+        );
+    };
+    println!("xlsx export was not a spreadsheet; falling back to CSV export for table '{}' ...", table);
+    Ok(Spreadsheet::Csv(table.clone(), download_google_sheet_as_csv(doc_url, export_base, retries)?))
+}
+
+/// Downloads the workbook as .xlsx. Returns `None` when the body is not a zip container (an xlsx
+/// always starts with `PK`), which is how the export's HTML error pages show up.
+fn download_google_sheet_as_xlsx(doc_url: &str, export_base: &str, retries: u32) -> Result<Option<TempPath>> {
+    let export = build_gsheet_export_url(doc_url, export_base, "xlsx")?;
+    let bytes = fetch_with_retries(&export, retries).context("Failed to download Google Sheet")?;
+    if !bytes.starts_with(b"PK") {
+        return Ok(None);
     }
-    let bytes = resp.bytes()?;
     let mut tmp = NamedTempFile::new()?;
     tmp.as_file_mut().write_all(&bytes)?;
-    let (_file, path) = tmp.keep()?;
-    Ok(path)
+    Ok(Some(tmp.into_temp_path()))
+}
+
+/// Downloads one tab (the URL's gid, or the first tab) through the CSV export as a string range.
+fn download_google_sheet_as_csv(doc_url: &str, export_base: &str, retries: u32) -> Result<calamine::Range<DataType>> {
+    let export = build_gsheet_export_url(doc_url, export_base, "csv")?;
+    let bytes = fetch_with_retries(&export, retries).context("Failed to download Google Sheet as CSV")?;
+    let text = String::from_utf8(bytes).context("CSV export is not valid UTF-8")?;
+    if text.trim_start().starts_with('<') {
+        bail!("Google Sheets CSV export returned HTML instead of CSV");
+    }
+    parse_csv(&text)
+}
+
+/// Parses a CSV export into a string range anchored at A1, so leading empty rows or columns keep
+/// their positions (`Range::from_sparse` would start the range at the first non-empty cell).
+/// Sheets exports every row at full width, so empty rows arrive as `,,` and are kept; the csv
+/// reader only drops lines with no separators at all.
+fn parse_csv(text: &str) -> Result<calamine::Range<DataType>> {
+    let mut rdr = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(text.as_bytes());
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for (i, record) in rdr.records().enumerate() {
+        let record = record.with_context(|| format!("CSV export row {}", i + 1))?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if rows.is_empty() || width == 0 {
+        return Ok(calamine::Range::empty());
+    }
+    let mut range = calamine::Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+    for (r, row) in rows.into_iter().enumerate() {
+        for (c, v) in row.into_iter().enumerate().filter(|(_, v)| !v.is_empty()) {
+            range.set_value((r as u32, c as u32), DataType::String(v));
+        }
+    }
+    Ok(range)
 }
 
 fn fetch_existing_tables(conn: &Connection) -> Result<HashMap<String, Table>> {
@@ -576,3 +997,394 @@ fn build_insert_sql(
 
     Ok((base_insert, vals))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zip_with(dir: &Path, name: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut w = zip::ZipWriter::new(&mut buf);
+            for (name, data) in entries {
+                w.start_file(*name, zip::write::FileOptions::default()).unwrap();
+                w.write_all(data).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        let path = dir.join(name);
+        fs::write(&path, buf.into_inner()).unwrap();
+        path
+    }
+
+    /// The parts of a minimal .xlsx holding one inline-string worksheet per `(name, rows)`.
+    fn xlsx_entries(sheets: &[(&str, &[&[&str]])]) -> Vec<(String, Vec<u8>)> {
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut workbook = String::from(
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+        );
+        let mut rels = String::from(r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#);
+        for (i, (name, rows)) in sheets.iter().enumerate() {
+            let n = i + 1;
+            workbook.push_str(&format!(r#"<sheet name="{name}" sheetId="{n}" r:id="rId{n}"/>"#));
+            rels.push_str(&format!(r#"<Relationship Id="rId{n}" Target="worksheets/sheet{n}.xml"/>"#));
+            let mut data = String::new();
+            for (r, row) in rows.iter().enumerate() {
+                data.push_str(&format!(r#"<row r="{}">"#, r + 1));
+                for (c, v) in row.iter().enumerate() {
+                    let cell = format!("{}{}", (b'A' + c as u8) as char, r + 1);
+                    data.push_str(&format!(r#"<c r="{cell}" t="inlineStr"><is><t>{v}</t></is></c>"#));
+                }
+                data.push_str("</row>");
+            }
+            let sheet = format!(
+                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{data}</sheetData></worksheet>"#
+            );
+            entries.push((format!("xl/worksheets/sheet{n}.xml"), sheet.into_bytes()));
+        }
+        workbook.push_str("</sheets></workbook>");
+        rels.push_str("</Relationships>");
+        entries.push(("xl/workbook.xml".into(), workbook.into_bytes()));
+        entries.push(("xl/_rels/workbook.xml.rels".into(), rels.into_bytes()));
+        entries
+    }
+
+    fn xlsx_with(dir: &Path, entries: &[(String, Vec<u8>)]) -> PathBuf {
+        let entries: Vec<(&str, &[u8])> = entries.iter().map(|(n, d)| (n.as_str(), d.as_slice())).collect();
+        zip_with(dir, "book.xlsx", &entries)
+    }
+
+    fn doors_db(dir: &Path) -> PathBuf {
+        let path = dir.join("tiles.db");
+        let conn = Connection::open(&path).unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        path
+    }
+
+    fn import_opts() -> ImportOptions<'static> {
+        ImportOptions {
+            dry_run: false,
+            truncate: &[],
+            sheets: &[],
+            validate_doors: false,
+            zip_entry: None,
+            download_retries: 0,
+            check_only: false,
+            plane_offset: 0,
+        }
+    }
+
+    #[test]
+    fn plane_offset_shifts_teleport_plane_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = doors_db(dir.path());
+        let xlsx = xlsx_with(
+            dir.path(),
+            &xlsx_entries(&[
+                (
+                    "teleports_door_nodes",
+                    &[
+                        &[
+                            "id",
+                            "direction",
+                            "tile_inside_x",
+                            "tile_inside_y",
+                            "tile_inside_plane",
+                            "tile_outside_x",
+                            "tile_outside_y",
+                            "tile_outside_plane",
+                        ],
+                        &["1", "IN", "10", "10", "0", "10", "11", "1"],
+                    ],
+                ),
+                ("teleports_lodestone_nodes", &[&["id", "lodestone", "dest_x", "dest_y", "dest_plane"], &["2", "LUMBRIDGE", "3233", "3221", "0"]]),
+            ]),
+        );
+
+        cmd_import_xlsx(xlsx.to_str().unwrap(), &db, &ImportOptions { plane_offset: 2, ..import_opts() }).unwrap();
+        let conn = Connection::open(&db).unwrap();
+        let door: (i64, i64, i64) = conn
+            .query_row("SELECT tile_inside_plane, tile_outside_plane, tile_inside_x FROM teleports_door_nodes", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(door, (2, 3, 10));
+        let lode: (i64, i64) =
+            conn.query_row("SELECT dest_plane, dest_x FROM teleports_lodestone_nodes", [], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!(lode, (2, 3233));
+    }
+
+    #[test]
+    fn shift_planes_only_touches_plane_columns() {
+        use rusqlite::types::Value;
+        let mut row: BTreeMap<String, Value> = [
+            ("plane", Value::Integer(1)),
+            ("Dest_Plane", Value::Integer(0)),
+            ("planet", Value::Integer(5)),
+            ("dest_x", Value::Integer(3200)),
+            ("orig_plane", Value::Null),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        shift_planes(&mut row, -1);
+        assert_eq!(row["plane"], Value::Integer(0));
+        assert_eq!(row["Dest_Plane"], Value::Integer(-1));
+        assert_eq!(row["planet"], Value::Integer(5));
+        assert_eq!(row["dest_x"], Value::Integer(3200));
+        assert_eq!(row["orig_plane"], Value::Null);
+    }
+
+    #[test]
+    fn door_validation_flags_distant_and_unidentified_doors() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(doors_db(dir.path())).unwrap();
+        conn.execute_batch(
+            "INSERT INTO teleports_door_nodes (id, direction, tile_inside_x, tile_inside_y, tile_inside_plane, \
+                 tile_outside_x, tile_outside_y, tile_outside_plane, real_id_open, real_id_closed) VALUES \
+             (1, 'IN', 10, 10, 0, 11, 11, 0, 100, NULL), \
+             (2, 'IN', 10, 10, 0, 12, 10, 0, 100, 101), \
+             (3, 'IN', 10, 10, 0, 10, 11, 1, NULL, 101), \
+             (4, 'IN', 10, 10, 0, 10, 11, 0, NULL, NULL), \
+             (5, 'IN', 10, NULL, 0, 10, 11, 0, 100, 101), \
+             (6, 'OUT', 5, 5, 2, 5, 4, 2, NULL, 7);",
+        )
+        .unwrap();
+
+        assert_eq!(validate_door_nodes(&conn).unwrap(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn check_reports_mapped_and_unmapped_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = doors_db(dir.path());
+        let xlsx = xlsx_with(
+            dir.path(),
+            &xlsx_entries(&[
+                ("teleports_door_nodes", &[&["ID", "direction", "not_a_column"], &["1", "N", "x"]]),
+                ("scratch", &[&["anything"]]),
+            ]),
+        );
+
+        let report = check_workbook(&xlsx, &db).unwrap();
+        assert_eq!(report.len(), 2);
+        let doors = &report[0];
+        assert_eq!(doors.table.as_deref(), Some("teleports_door_nodes"));
+        assert_eq!(doors.mapped, vec![("ID".to_string(), "id".to_string()), ("direction".into(), "direction".into())]);
+        assert_eq!(doors.unmapped, vec!["not_a_column".to_string()]);
+        assert_eq!(report[1].sheet, "scratch");
+        assert!(report[1].table.is_none());
+    }
+
+    #[test]
+    fn check_fails_on_an_unreadable_sheet() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = doors_db(dir.path());
+        // Truncate the worksheet XML so calamine lists the sheet but cannot read it.
+        let mut entries = xlsx_entries(&[("teleports_door_nodes", &[&["id"]])]);
+        entries[0].1 = b"<worksheet><sheetData><row>".to_vec();
+        let xlsx = xlsx_with(dir.path(), &entries);
+
+        let err = check_workbook(&xlsx, &db).unwrap_err();
+        assert!(format!("{err:#}").contains("Read worksheet 'teleports_door_nodes'"), "{err:#}");
+    }
+
+    #[test]
+    fn csv_keeps_leading_empty_rows_and_columns() {
+        let range = parse_csv(",,\n,id,\"name, quoted \"\"x\"\"\"\r\n,7,\"multi\nline\"\n").unwrap();
+        assert_eq!(range.start(), Some((0, 0)));
+        assert_eq!(range.get_size(), (3, 3));
+        assert_eq!(range.get_value((1, 1)), Some(&DataType::String("id".into())));
+        assert_eq!(range.get_value((1, 2)), Some(&DataType::String("name, quoted \"x\"".into())));
+        assert_eq!(range.get_value((2, 2)), Some(&DataType::String("multi\nline".into())));
+        assert_eq!(range.get_value((0, 0)), Some(&DataType::Empty));
+        assert_eq!(range.get_value((2, 0)), Some(&DataType::Empty));
+    }
+
+    #[test]
+    fn csv_without_cells_is_an_empty_range() {
+        assert!(parse_csv("").unwrap().is_empty());
+        assert!(parse_csv("\n\n").unwrap().is_empty());
+        assert_eq!(parse_csv(",,\n,,\n").unwrap().rows().flatten().filter(|c| !c.is_empty()).count(), 0);
+    }
+
+    /// Serves one canned response per connection, in order, on a local port. Returns the server's
+    /// base URL and a handle yielding the request line of each request served.
+    fn serve<R: AsRef<str> + Send + 'static>(responses: Vec<R>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let mut req = Vec::new();
+                while !req.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = std::io::Read::read(&mut stream, &mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    req.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8_lossy(&req).lines().next().unwrap_or_default().to_string());
+                stream.write_all(response.as_ref().as_bytes()).unwrap();
+            }
+            requests
+        });
+        (base, handle)
+    }
+
+    fn ok_response(content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        )
+    }
+
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[test]
+    fn download_retries_through_503s() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello";
+        let (base, server) = serve(vec![UNAVAILABLE, UNAVAILABLE, ok]);
+        assert_eq!(fetch_with_retries(&format!("{base}/book.xlsx"), 2).unwrap(), b"hello");
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn download_gives_up_after_the_last_retry() {
+        let (base, server) = serve(vec![UNAVAILABLE, UNAVAILABLE]);
+        let err = fetch_with_retries(&format!("{base}/book.xlsx"), 1).unwrap_err();
+        assert!(err.to_string().contains("after 2 attempt(s)"), "{err}");
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn download_retries_a_truncated_body() {
+        let short = "HTTP/1.1 200 OK\r\nContent-Length: 50\r\nConnection: close\r\n\r\npartial";
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+        let (base, server) = serve(vec![short, UNAVAILABLE, ok]);
+        assert_eq!(fetch_with_retries(&format!("{base}/book.xlsx"), 2).unwrap(), b"ok");
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    const SHEET_URL: &str = "https://docs.google.com/spreadsheets/d/abc123/edit#gid=42";
+
+    #[test]
+    fn google_sheet_falls_back_to_csv_when_xlsx_export_is_html() {
+        let html = ok_response("text/html", "<html><body>Sorry, try again later</body></html>");
+        let csv = ok_response("text/csv", "id,lodestone,dest_plane\r\n1,LUMBRIDGE,0\r\n");
+        let (base, server) = serve(vec![html, csv]);
+
+        let table = vec!["teleports_lodestone_nodes".to_string()];
+        let Spreadsheet::Csv(name, range) = download_google_sheet(SHEET_URL, &base, &table, 0).unwrap() else {
+            panic!("expected the CSV fallback");
+        };
+        assert_eq!(name, "teleports_lodestone_nodes");
+        assert_eq!(range.get_size(), (2, 3));
+        assert_eq!(range.get_value((1, 1)), Some(&DataType::String("LUMBRIDGE".into())));
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /spreadsheets/d/abc123/export?format=xlsx&gid=42 HTTP/1.1",
+                "GET /spreadsheets/d/abc123/export?format=csv&gid=42 HTTP/1.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn google_sheet_csv_fallback_needs_one_table() {
+        let (base, server) = serve(vec![ok_response("text/html", "<html></html>")]);
+        let err = download_google_sheet(SHEET_URL, &base, &[], 0).err().unwrap();
+        assert!(err.to_string().contains("single --sheets entry"), "{err}");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn google_sheet_xlsx_export_is_used_when_it_is_a_zip() {
+        let (base, server) = serve(vec![ok_response("application/octet-stream", "PK fake xlsx")]);
+        let Spreadsheet::Workbook(tmp) = download_google_sheet(SHEET_URL, &base, &[], 0).unwrap() else {
+            panic!("expected the xlsx export");
+        };
+        assert_eq!(fs::read(&tmp).unwrap(), b"PK fake xlsx");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-5", now), None);
+    }
+
+    fn extracted_workbook(zip: &Path, entry: Option<&str>) -> TempPath {
+        match extract_from_zip(zip, entry).unwrap() {
+            Spreadsheet::Workbook(tmp) => tmp,
+            Spreadsheet::Csv(table, _) => panic!("expected a workbook, got CSV for {table}"),
+        }
+    }
+
+    #[test]
+    fn zip_extraction_is_removed_when_the_guard_drops() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = zip_with(dir.path(), "book.zip", &[("readme.txt", b"hi"), ("data/book.xlsx", b"PK fake")]);
+
+        let tmp = extracted_workbook(&zip, None);
+        let path = tmp.to_path_buf();
+        assert_eq!(fs::read(&path).unwrap(), b"PK fake");
+        drop(tmp);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn zip_with_several_spreadsheets_needs_an_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = zip_with(dir.path(), "book.zip", &[("a.xlsx", b"A"), ("b.csv", b"id\n1\n")]);
+
+        let err = extract_from_zip(&zip, None).err().unwrap();
+        assert!(err.to_string().contains("--zip-entry"), "{err}");
+        assert_eq!(fs::read(extracted_workbook(&zip, Some("a.xlsx"))).unwrap(), b"A");
+        assert!(matches!(extract_from_zip(&zip, Some("b.csv")).unwrap(), Spreadsheet::Csv(t, _) if t == "b"));
+        assert!(extract_from_zip(&zip, Some("c.xlsx")).is_err());
+    }
+
+    fn lodestones(db: &Path) -> Vec<(i64, String, i64)> {
+        let conn = Connection::open(db).unwrap();
+        let mut stmt = conn.prepare("SELECT id, lodestone, dest_x FROM teleports_lodestone_nodes ORDER BY id").unwrap();
+        stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).unwrap().map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn imports_a_workbook_from_a_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = doors_db(dir.path());
+        let xlsx = xlsx_with(
+            dir.path(),
+            &xlsx_entries(&[(
+                "teleports_lodestone_nodes",
+                &[&["id", "lodestone", "dest_x", "dest_y", "dest_plane"], &["1", "LUMBRIDGE", "3233", "3221", "0"]],
+            )]),
+        );
+        let bytes = fs::read(&xlsx).unwrap();
+        let zip = zip_with(dir.path(), "release.zip", &[("README", b"notes"), ("sheets/teleports.xlsx", &bytes)]);
+
+        cmd_import_xlsx(zip.to_str().unwrap(), &db, &import_opts()).unwrap();
+        assert_eq!(lodestones(&db), vec![(1, "LUMBRIDGE".to_string(), 3233)]);
+    }
+
+    #[test]
+    fn imports_a_csv_entry_from_a_zip_into_the_table_named_by_its_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = doors_db(dir.path());
+        let csv = b"id,lodestone,dest_x,dest_y,dest_plane\r\n1,LUMBRIDGE,3233,3221,0\r\n2,\"BURTHORPE\",2899,3544,0\r\n";
+        let zip = zip_with(dir.path(), "release.zip", &[("data/teleports_lodestone_nodes.csv", csv)]);
+
+        cmd_import_xlsx(zip.to_str().unwrap(), &db, &import_opts()).unwrap();
+        assert_eq!(lodestones(&db), vec![(1, "LUMBRIDGE".to_string(), 3233), (2, "BURTHORPE".to_string(), 2899)]);
+    }
+}
@@ -1,20 +1,18 @@
 use anyhow::{Context, Result};
 use rusqlite::Connection;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 use rayon::prelude::*;
+use flate2::read::GzDecoder;
 
-#[derive(Deserialize, Debug)]
-struct FileRoot {
-    #[serde(default)]
-    chunk: Option<Chunk>,
-    #[serde(default)]
-    tiles: Vec<Tile>,
-}
+use crate::db::SqliteMode;
 
 #[derive(Deserialize, Debug, Default)]
 struct Chunk {
@@ -26,6 +24,8 @@ struct Chunk {
     chunk_size: Option<i64>,
 }
 
+/// One entry of a tile file's `tiles` array. A tile without any mask field is stored with a NULL
+/// `walk_mask`, which reads as blocked (same as 0) everywhere downstream.
 #[derive(Deserialize, Debug)]
 struct Tile {
     x: i64,
@@ -33,44 +33,143 @@ struct Tile {
     plane: i64,
     #[serde(rename = "walkMask", default)]
     walk_mask: Option<i64>,
+    /// Same bits as `walkMask`, written as a hex string (e.g. "0x0F"); `walkMask` wins if both are set.
+    #[serde(rename = "walkMaskHex", default)]
+    walk_mask_hex: Option<String>,
+    /// Direction name -> allowed (e.g. `{"NorthEast": true}`); used when neither mask field is set.
+    #[serde(rename = "walkData", default)]
+    walk_data: Option<HashMap<String, bool>>,
+}
+
+impl Tile {
+    // walk_mask bit -> direction (bit 0..7):
+    // left, bottom, right, top, topleft, bottomleft, bottomright, topright
+    fn resolved_walk_mask(&self) -> Result<Option<i64>> {
+        if self.walk_mask.is_some() {
+            return Ok(self.walk_mask);
+        }
+        if let Some(hex) = &self.walk_mask_hex {
+            return parse_walk_mask_hex(hex)
+                .map(Some)
+                .with_context(|| format!("invalid walkMaskHex at ({}, {}, {})", self.x, self.y, self.plane));
+        }
+        match &self.walk_data {
+            Some(data) => crate::navmesh::walk_data_to_mask(data)
+                .map(Some)
+                .with_context(|| format!("invalid walkData at ({}, {}, {})", self.x, self.y, self.plane)),
+            None => Ok(None),
+        }
+    }
 }
 
-pub fn cmd_load_tiles(json_folder: &Path, db_path: &Path, overrides_path: Option<&Path>) -> Result<()> {
-    println!("Using JSON folder: {}", json_folder.display());
+fn parse_walk_mask_hex(s: &str) -> Result<i64> {
+    let trimmed = s.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    let mask = i64::from_str_radix(digits, 16)
+        .with_context(|| format!("parse hex walk mask '{}'", s))?;
+    if !(0..=0xFF).contains(&mask) {
+        anyhow::bail!("hex walk mask '{}' does not fit in 8 direction bits", s);
+    }
+    Ok(mask)
+}
+
+pub struct LoadOptions<'a> {
+    pub overrides_path: Option<&'a Path>,
+    pub overrides_format: Option<OverridesFormat>,
+    pub sqlite_mode: SqliteMode,
+    pub strict: bool,
+    pub threads: Option<usize>,
+    /// Skip (and count) tiles that fail to parse instead of rejecting their whole file.
+    pub skip_bad_tiles: bool,
+    /// Added to every tile's plane (not to overrides, which are already in DB coordinates).
+    pub plane_offset: i64,
+}
+
+pub fn cmd_load_tiles(json_source: &Path, db_path: &Path, opts: &LoadOptions) -> Result<()> {
+    let LoadOptions { overrides_path, overrides_format, sqlite_mode, strict, threads, skip_bad_tiles, plane_offset } =
+        *opts;
+    println!("Using JSON source: {}", json_source.display());
     println!("Using DB file    : {}", db_path.display());
 
     let mut conn = Connection::open(db_path)
         .with_context(|| format!("Failed to open DB at {}", db_path.display()))?;
 
-    conn.execute_batch(
-        "PRAGMA journal_mode=WAL;\nPRAGMA synchronous=NORMAL;\nPRAGMA temp_store=MEMORY;",
-    )?;
+    sqlite_mode.apply(&conn)?;
 
-    crate::db::create_tables(&mut conn)?;
-    load_json_files(json_folder, &mut conn)?;
+    let pool = crate::util::build_thread_pool(threads)?;
+    if plane_offset != 0 {
+        println!("Shifting tile planes by {}", plane_offset);
+    }
+    load_json_files(json_source, &mut conn, strict, skip_bad_tiles, plane_offset, &pool)?;
 
     if let Some(overrides_path) = overrides_path {
-        apply_overrides_file(overrides_path, &mut conn)?;
+        apply_overrides_file(overrides_path, overrides_format, &mut conn)?;
     }
 
     println!("Tiles successfully loaded into {}", db_path.display());
     Ok(())
 }
 
-fn apply_overrides_file(path: &Path, conn: &mut Connection) -> Result<()> {
-    if !path.exists() {
-        anyhow::bail!("Overrides file not found: {}", path.display());
+/// On-disk format of the `--overrides` file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OverridesFormat {
+    /// One `x,y,z,walk_mask` line per tile
+    Csv,
+    /// Array of `{x, y, plane, walk_mask?, blocked?, walk_data?}` objects
+    Json,
+}
+
+impl OverridesFormat {
+    fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => OverridesFormat::Json,
+            _ => OverridesFormat::Csv,
+        }
     }
+}
+
+#[derive(Deserialize, Debug)]
+struct OverrideEntry {
+    x: i64,
+    y: i64,
+    #[serde(alias = "z")]
+    plane: i64,
+    #[serde(alias = "walkMask", default)]
+    walk_mask: Option<i64>,
+    #[serde(default)]
+    blocked: Option<bool>,
+    /// Direction name (walk_mask names or synonyms such as `northeast`) -> allowed.
+    #[serde(alias = "walkData", default)]
+    walk_data: Option<HashMap<String, bool>>,
+}
+
+impl OverrideEntry {
+    // `blocked: true` forces a 0 mask; otherwise walk_mask wins over walk_data.
+    fn resolved_walk_mask(&self) -> Result<i64> {
+        if self.blocked == Some(true) {
+            return Ok(0);
+        }
+        if let Some(mask) = self.walk_mask {
+            return Ok(mask);
+        }
+        let Some(data) = &self.walk_data else {
+            // `blocked: false` alone says nothing about which directions are open.
+            if self.blocked == Some(false) {
+                anyhow::bail!("blocked: false needs walk_mask or walk_data to say which directions are open");
+            }
+            anyhow::bail!("one of walk_mask, walk_data or blocked is required");
+        };
+        crate::navmesh::walk_data_to_mask(data)
+    }
+}
 
-    println!("Applying overrides from {}...", path.display());
+fn read_csv_overrides(path: &Path) -> Result<Vec<(i64, i64, i64, i64)>> {
     let file = File::open(path).with_context(|| format!("open overrides file {}", path.display()))?;
     let reader = BufReader::new(file);
-
-    let tx = conn.transaction()?;
-    let mut stmt = tx.prepare(
-        "INSERT INTO tiles (x, y, plane, walk_mask, RegionID) VALUES (?1, ?2, ?3, ?4, ?5) \
-         ON CONFLICT(x, y, plane) DO UPDATE SET walk_mask=excluded.walk_mask, RegionID=excluded.RegionID",
-    )?;
+    let mut out = Vec::new();
 
     for (idx, line_res) in reader.lines().enumerate() {
         let line_no = idx + 1;
@@ -93,7 +192,47 @@ fn apply_overrides_file(path: &Path, conn: &mut Connection) -> Result<()> {
         let y: i64 = parts[1].parse().with_context(|| format!("parse y on line {}", line_no))?;
         let plane: i64 = parts[2].parse().with_context(|| format!("parse z on line {}", line_no))?;
         let walk_mask: i64 = parts[3].parse().with_context(|| format!("parse walk_mask on line {}", line_no))?;
+        out.push((x, y, plane, walk_mask));
+    }
+    Ok(out)
+}
 
+fn read_json_overrides(path: &Path) -> Result<Vec<(i64, i64, i64, i64)>> {
+    let file = File::open(path).with_context(|| format!("open overrides file {}", path.display()))?;
+    let mut de = serde_json::Deserializer::from_reader(BufReader::new(file));
+    let entries: Vec<OverrideEntry> = serde_path_to_error::deserialize(&mut de)
+        .with_context(|| format!("Invalid JSON overrides in {}", path.display()))?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let mask = e
+                .resolved_walk_mask()
+                .with_context(|| format!("overrides entry {} ({}, {}, {}) in {}", i, e.x, e.y, e.plane, path.display()))?;
+            Ok((e.x, e.y, e.plane, mask))
+        })
+        .collect()
+}
+
+fn apply_overrides_file(path: &Path, format: Option<OverridesFormat>, conn: &mut Connection) -> Result<()> {
+    if !path.exists() {
+        anyhow::bail!("Overrides file not found: {}", path.display());
+    }
+
+    let format = format.unwrap_or_else(|| OverridesFormat::detect(path));
+    println!("Applying {:?} overrides from {}...", format, path.display());
+    let overrides = match format {
+        OverridesFormat::Csv => read_csv_overrides(path)?,
+        OverridesFormat::Json => read_json_overrides(path)?,
+    };
+
+    let tx = conn.transaction()?;
+    let mut stmt = tx.prepare(
+        "INSERT INTO tiles (x, y, plane, walk_mask, RegionID) VALUES (?1, ?2, ?3, ?4, ?5) \
+         ON CONFLICT(x, y, plane) DO UPDATE SET walk_mask=excluded.walk_mask, RegionID=excluded.RegionID",
+    )?;
+
+    for &(x, y, plane, walk_mask) in &overrides {
         let region_x = x >> 6;
         let region_y = y >> 6;
         let region_id = (region_x << 8) + region_y;
@@ -103,62 +242,166 @@ fn apply_overrides_file(path: &Path, conn: &mut Connection) -> Result<()> {
 
     drop(stmt);
     tx.commit()?;
+    println!("Applied {} tile overrides", overrides.len());
     Ok(())
 }
 
-fn load_json_files(folder: &Path, conn: &mut Connection) -> Result<()> {
-    if !folder.exists() {
-        anyhow::bail!("JSON folder not found: {}", folder.display());
+fn is_json_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.ends_with(".json") || name.ends_with(".json.gz")
+}
+
+fn load_json_files(
+    source: &Path,
+    conn: &mut Connection,
+    strict: bool,
+    skip_bad_tiles: bool,
+    plane_offset: i64,
+    pool: &std::sync::Arc<rayon::ThreadPool>,
+) -> Result<()> {
+    if !source.exists() {
+        anyhow::bail!("JSON source not found: {}", source.display());
     }
 
-    // Gather JSON files
-    let mut file_entries: Vec<_> = fs::read_dir(folder)?
-        .filter_map(|e| e.ok().map(|e| e.path()))
-        .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
-        .collect();
+    // Gather JSON files: either the single file given, or every *.json / *.json.gz in the folder
+    let single_file = source.is_file();
+    let mut file_entries: Vec<_> = if single_file {
+        vec![source.to_path_buf()]
+    } else {
+        fs::read_dir(source)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| is_json_file(p))
+            .collect()
+    };
     file_entries.sort();
 
-    // Channel for streaming parsed batches to a single DB writer
-    let (tx_msg, rx_msg) = mpsc::channel::<FileBatch>();
+    // Optimize SQLite for bulk load and avoid maintaining indexes during insert
+    conn.execute_batch("PRAGMA foreign_keys=OFF;")?;
+
+    // One transaction covers the schema and every row, so a failed load (e.g. under --strict)
+    // leaves the DB without tables and the same command can simply be rerun.
+    let txw = conn.transaction()?;
+    crate::db::create_tables(&txw)?;
+    txw.execute_batch("DROP INDEX IF EXISTS idx_tiles_walkable;")?;
+
+    // Bounded channel for streaming parsed batches to a single DB writer: producers block once
+    // the writer falls `CHANNEL_BATCHES` batches behind instead of buffering whole files.
+    let (tx_msg, rx_msg) = mpsc::sync_channel::<FileBatch>(CHANNEL_BATCHES);
 
-    // Spawn parallel producers to read/parse JSON files and send batches
+    // Spawn producers to read/parse JSON files and send batches: one producer for a single
+    // (possibly huge) file, parallel producers for a directory of files
+    // Each producer reports the files it rejected so the writer can summarize them.
     let producer = {
         let tx_msg = tx_msg.clone();
-        thread::spawn(move || {
-            file_entries
-                .into_par_iter()
-                .for_each_with(tx_msg, |s, path| {
-                    if let Err(e) = parse_file_and_stream(&path, s) {
-                        eprintln!("Error processing {}: {}", path.display(), e);
-                    }
-                });
+        let pool = pool.clone();
+        thread::spawn(move || -> Vec<(PathBuf, anyhow::Error)> {
+            let parse = ParseOptions { skip_bad_tiles, plane_offset, batch_size: SUB_BATCH };
+            let process = |path: PathBuf, s: &mpsc::SyncSender<FileBatch>| match parse_file_and_stream(&path, s, parse) {
+                Ok(()) => None,
+                Err(e) => {
+                    eprintln!("Error processing {}: {:#}", path.display(), e);
+                    Some((path, e))
+                }
+            };
+            if single_file {
+                return file_entries.into_iter().filter_map(|p| process(p, &tx_msg)).collect();
+            }
+            pool.install(|| {
+                file_entries
+                    .into_par_iter()
+                    .map_with(tx_msg, |s, path| process(path, s))
+                    .flatten()
+                    .collect()
+            })
             // Dropping sender closes the channel
         })
     };
 
     drop(tx_msg);
 
-    // Optimize SQLite for bulk load and avoid maintaining indexes during insert
-    conn.execute_batch(
-        "PRAGMA foreign_keys=OFF;\nDROP INDEX IF EXISTS idx_tiles_walkable;",
-    )?;
-
-    // Single transaction and prepared statement reused for entire stream
-    let txw = conn.transaction()?;
+    // Prepared statements reused for the entire stream
+    // The DB starts empty, so a conflicting insert means the same (x, y, plane) appeared twice in
+    // this load. The later row still wins, but the overwrite is counted against its file.
     let mut tiles_stmt = txw.prepare(
-        "INSERT OR REPLACE INTO tiles (x, y, plane, walk_mask, RegionID) VALUES (?, ?, ?, ?, ?)",
+        "INSERT OR IGNORE INTO tiles (x, y, plane, walk_mask, RegionID) VALUES (?, ?, ?, ?, ?)",
     )?;
+    let mut replace_stmt = txw.prepare(
+        "UPDATE tiles SET walk_mask = ?4, RegionID = ?5 WHERE x = ?1 AND y = ?2 AND plane = ?3",
+    )?;
+    let mut collisions: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    let mut skipped: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    let mut missing_mask = 0usize;
 
     // Drain messages as they arrive and insert rows
     for batch in rx_msg {
+        if batch.skipped_tiles > 0 {
+            *skipped.entry(batch.source.clone()).or_default() += batch.skipped_tiles;
+        }
         if batch.tile_rows.is_empty() { continue; }
         for row in batch.tile_rows {
             let (x, y, plane, walk_mask, region_id) = row;
-            tiles_stmt.execute(rusqlite::params![x, y, plane, walk_mask, region_id])?;
+            if walk_mask.is_none() {
+                missing_mask += 1;
+            }
+            let params = rusqlite::params![x, y, plane, walk_mask, region_id];
+            if tiles_stmt.execute(params)? == 0 {
+                replace_stmt.execute(params)?;
+                *collisions.entry(batch.source.clone()).or_default() += 1;
+            }
         }
     }
 
     drop(tiles_stmt);
+    drop(replace_stmt);
+
+    // The channel only closes once every producer is done, so this join does not block.
+    let mut rejected = producer
+        .join()
+        .map_err(|_| anyhow::anyhow!("JSON producer thread panicked"))?;
+    if single_file {
+        // With only one file there is nothing to salvage: fail (and roll back) whatever the mode.
+        if let Some((_, e)) = rejected.pop() {
+            return Err(e);
+        }
+    }
+    if !rejected.is_empty() {
+        rejected.sort_by(|a, b| a.0.cmp(&b.0));
+        println!("Rejected {} tile file(s):", rejected.len());
+        for (path, e) in &rejected {
+            println!("  {}: {:#}", path.display(), e);
+        }
+        if strict {
+            // Dropping the transaction rolls back the schema and every row inserted so far.
+            anyhow::bail!(
+                "--strict: {} tile file(s) failed validation; rolled back, the DB has no tables",
+                rejected.len()
+            );
+        }
+    }
+    if missing_mask > 0 {
+        println!(
+            "Warning: {} tile(s) had no walkMask, walkMaskHex or walkData; stored with NULL walk_mask, which every command treats as blocked",
+            missing_mask
+        );
+    }
+    if !skipped.is_empty() {
+        println!("Skipped {} malformed tile(s):", skipped.values().sum::<usize>());
+        for (path, n) in &skipped {
+            println!("  {}: {}", path.display(), n);
+        }
+    }
+    if !collisions.is_empty() {
+        let total: usize = collisions.values().sum();
+        // Directory loads parse files in parallel, so which duplicate wins is not deterministic.
+        println!("{} tile(s) appeared more than once and overwrote an earlier row; overwrites per file:", total);
+        for (path, n) in &collisions {
+            println!("  {}: {}", path.display(), n);
+        }
+        if strict {
+            anyhow::bail!("--strict: {} duplicate tile(s) across the loaded files; rolled back, the DB has no tables", total);
+        }
+    }
+
     txw.commit()?;
 
     // Recreate index and restore FK checks after load
@@ -166,8 +409,6 @@ fn load_json_files(folder: &Path, conn: &mut Connection) -> Result<()> {
         "CREATE INDEX IF NOT EXISTS idx_tiles_walkable ON tiles(x, y, plane);\nPRAGMA foreign_keys=ON;",
     )?;
 
-    // Ensure producers are finished
-    let _ = producer.join();
     Ok(())
 }
 
@@ -180,54 +421,481 @@ type TileRow = (
 );
 
 struct FileBatch {
+    source: PathBuf,
     tile_rows: Vec<TileRow>,
+    /// Tiles dropped under `--skip-bad-tiles` since the previous batch from this file.
+    skipped_tiles: usize,
 }
 
-fn parse_file_and_stream(path: &Path, sender: &mpsc::Sender<FileBatch>) -> Result<()> {
-    println!("Loading {}...", path.display());
-    let file = File::open(&path).with_context(|| format!("open {}", path.display()))?;
-    let reader = BufReader::new(file);
-    let data: FileRoot = serde_json::from_reader(reader)
-        .with_context(|| format!("parse JSON {}", path.display()))?;
+/// Opens a tiles JSON file, transparently decompressing it when the name ends in `.gz`.
+fn open_json_reader(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let is_gz = path.extension().map(|e| e == "gz").unwrap_or(false);
+    if is_gz {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+// Tiles per batch sent to the writer; also the most tiles of one file held in memory at once.
+const SUB_BATCH: usize = 1_000_000;
+// Batches queued between the parsers and the writer before the parsers block.
+const CHANNEL_BATCHES: usize = 4;
+
+/// How each tile file is parsed; the same for every file of a load.
+#[derive(Clone, Copy)]
+struct ParseOptions {
+    skip_bad_tiles: bool,
+    plane_offset: i64,
+    /// Tiles per batch sent to the writer (`SUB_BATCH` outside tests).
+    batch_size: usize,
+}
 
-    if data.tiles.is_empty() {
-        return Ok(());
+fn tile_row(t: &Tile, plane_offset: i64) -> Result<TileRow> {
+    // Compute RegionID from x,y: regionId = (regionX << 8) + regionY,
+    // where regionX = x >> 6 and regionY = y >> 6
+    let region_x = t.x >> 6;
+    let region_y = t.y >> 6;
+    let region_id = (region_x << 8) + region_y;
+    let plane = t.plane + plane_offset;
+    if plane < 0 {
+        anyhow::bail!("plane {} at ({}, {}) is negative after --plane-offset {}", t.plane, t.x, t.y, plane_offset);
     }
+    Ok((t.x, t.y, plane, t.resolved_walk_mask()?, region_id))
+}
+
+/// Walks a tile file's top-level object, handing the `tiles` array to `TilesSeq` instead of
+/// collecting it. `chunk` is still type-checked; other keys are skipped, and a second `tiles` key
+/// is an error. Yields the rows of the last, not yet sent, batch.
+struct TileFileVisitor<'a> {
+    source: &'a Path,
+    sender: &'a mpsc::SyncSender<FileBatch>,
+    opts: ParseOptions,
+}
 
+impl<'de> Visitor<'de> for TileFileVisitor<'_> {
+    type Value = PendingBatch;
 
-    // Send chunk meta first so writer can insert chunk row
-    sender.send(FileBatch {
-        tile_rows: Vec::new(),
-    }).map_err(|e| anyhow::anyhow!(e))?;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tile file object with a `tiles` array")
+    }
 
-    // Stream tile rows in small chunks to bound memory
-    const SUB_BATCH: usize = 1_000_000;
-    let mut rows: Vec<TileRow> = Vec::with_capacity(SUB_BATCH);
-    for t in data.tiles.into_iter() {
-        // Compute RegionID from x,y: regionId = (regionX << 8) + regionY,
-        // where regionX = x >> 6 and regionY = y >> 6
-        let region_x = t.x >> 6;
-        let region_y = t.y >> 6;
-        let region_id = (region_x << 8) + region_y;
-        rows.push((
-            t.x,
-            t.y,
-            t.plane,
-            t.walk_mask,
-            region_id,
-        ));
-        if rows.len() >= SUB_BATCH {
-            sender.send(FileBatch {
-                tile_rows: std::mem::take(&mut rows),
-            }).map_err(|e| anyhow::anyhow!(e))?;
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<PendingBatch, A::Error> {
+        let mut pending: Option<PendingBatch> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "tiles" => {
+                    if pending.is_some() {
+                        return Err(de::Error::duplicate_field("tiles"));
+                    }
+                    pending =
+                        Some(map.next_value_seed(TilesSeq { source: self.source, sender: self.sender, opts: self.opts })?);
+                }
+                "chunk" => {
+                    map.next_value::<Option<Chunk>>()?;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
         }
+        Ok(pending.unwrap_or_default())
     }
+}
+
+/// Rows (and skipped-tile count) parsed since the last batch sent; held back until the whole file
+/// has parsed, so a file rejected by a late error only loses its earlier full batches.
+#[derive(Default)]
+struct PendingBatch {
+    rows: Vec<TileRow>,
+    skipped: usize,
+}
+
+/// Converts tiles one element at a time and sends them on in `batch_size`-sized batches, returning
+/// the final partial batch. With `skip_bad_tiles`, each element is first read as a generic JSON value so a tile with bad
+/// fields can be dropped without losing its place in the array; JSON syntax errors still abort.
+struct TilesSeq<'a> {
+    source: &'a Path,
+    sender: &'a mpsc::SyncSender<FileBatch>,
+    opts: ParseOptions,
+}
 
-    if !rows.is_empty() {
-        sender.send(FileBatch {
-            tile_rows: rows,
-        }).map_err(|e| anyhow::anyhow!(e))?;
+// Per file, only the first few skipped tiles are printed; the rest are only counted.
+const MAX_SKIP_REPORTS: usize = 5;
+
+impl<'de> DeserializeSeed<'de> for TilesSeq<'_> {
+    type Value = PendingBatch;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<PendingBatch, D::Error> {
+        deserializer.deserialize_seq(self)
     }
+}
 
+impl<'de> Visitor<'de> for TilesSeq<'_> {
+    type Value = PendingBatch;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of tiles")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<PendingBatch, A::Error> {
+        let send = |rows: Vec<TileRow>, skipped_tiles: usize| {
+            send_batch(self.sender, self.source, rows, skipped_tiles).map_err(de::Error::custom)
+        };
+        let mut rows: Vec<TileRow> = Vec::new();
+        let mut skipped = 0usize;
+        let mut skipped_total = 0usize;
+        let mut index = 0usize;
+        loop {
+            let row = if self.opts.skip_bad_tiles {
+                let Some(v) = seq.next_element::<serde_json::Value>()? else { break };
+                let row = serde_json::from_value::<Tile>(v)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|t| tile_row(&t, self.opts.plane_offset));
+                match row {
+                    Ok(row) => row,
+                    Err(e) => {
+                        if skipped_total < MAX_SKIP_REPORTS {
+                            println!("  Skipping tiles[{}] in {}: {:#}", index, self.source.display(), e);
+                        }
+                        skipped += 1;
+                        skipped_total += 1;
+                        index += 1;
+                        continue;
+                    }
+                }
+            } else {
+                let Some(t) = seq.next_element::<Tile>()? else { break };
+                tile_row(&t, self.opts.plane_offset).map_err(|e| de::Error::custom(format!("{:#}", e)))?
+            };
+            rows.push(row);
+            index += 1;
+            if rows.len() >= self.opts.batch_size {
+                send(std::mem::take(&mut rows), std::mem::take(&mut skipped))?;
+            }
+        }
+        Ok(PendingBatch { rows, skipped })
+    }
+}
+
+fn send_batch(sender: &mpsc::SyncSender<FileBatch>, source: &Path, rows: Vec<TileRow>, skipped_tiles: usize) -> Result<()> {
+    sender
+        .send(FileBatch { source: source.to_path_buf(), tile_rows: rows, skipped_tiles })
+        .map_err(|_| anyhow::anyhow!("tile writer stopped receiving"))
+}
+
+/// Parses one tile file and streams its rows to the writer without holding the whole `tiles`
+/// array in memory. A file that fails part way through (including trailing data after the
+/// top-level object) has already sent its earlier full batches, so only files above `batch_size`
+/// tiles can be partially loaded outside `--strict`.
+fn parse_file_and_stream(path: &Path, sender: &mpsc::SyncSender<FileBatch>, opts: ParseOptions) -> Result<()> {
+    println!("Loading {}...", path.display());
+    let reader = open_json_reader(path)?;
+    // serde_path_to_error reports the failing field (e.g. `tiles[12].x`) alongside line/column.
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let mut track = serde_path_to_error::Track::new();
+    let res = serde_path_to_error::Deserializer::new(&mut de, &mut track)
+        .deserialize_map(TileFileVisitor { source: path, sender, opts });
+    let pending = res
+        .map_err(|e| serde_path_to_error::Error::new(track.path(), e))
+        .with_context(|| format!("parse JSON {}", path.display()))?;
+    de.end().with_context(|| format!("parse JSON {}: trailing data after the tile object", path.display()))?;
+    if !pending.rows.is_empty() || pending.skipped > 0 {
+        send_batch(sender, path, pending.rows, pending.skipped)?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `json` as a tile file through a one-slot channel; returns the result and the rows received.
+    fn stream(json: &str, skip_bad_tiles: bool) -> (Result<()>, Vec<TileRow>) {
+        stream_shifted(json, skip_bad_tiles, 0)
+    }
+
+    fn stream_shifted(json: &str, skip_bad_tiles: bool, plane_offset: i64) -> (Result<()>, Vec<TileRow>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiles.json");
+        fs::write(&path, json).unwrap();
+        let (tx, rx) = mpsc::sync_channel::<FileBatch>(1);
+        let writer = thread::spawn(move || rx.into_iter().flat_map(|b| b.tile_rows).collect::<Vec<_>>());
+        let res = parse_file_and_stream(&path, &tx, ParseOptions { skip_bad_tiles, plane_offset, batch_size: SUB_BATCH });
+        drop(tx);
+        (res, writer.join().unwrap())
+    }
+
+    fn opts() -> LoadOptions<'static> {
+        LoadOptions {
+            overrides_path: None,
+            overrides_format: None,
+            sqlite_mode: SqliteMode::Fast,
+            strict: false,
+            threads: Some(2),
+            skip_bad_tiles: false,
+            plane_offset: 0,
+        }
+    }
+
+    fn table_count(db: &Path) -> i64 {
+        Connection::open(db)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type='table'", [], |r| r.get(0))
+            .unwrap()
+    }
+
+    fn tile_count(db: &Path) -> i64 {
+        Connection::open(db).unwrap().query_row("SELECT COUNT(*) FROM tiles", [], |r| r.get(0)).unwrap()
+    }
+
+    /// A directory with one valid and one structurally invalid tile file.
+    fn good_and_bad_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a_good.json"),
+            r#"{"tiles": [{"x": 1, "y": 1, "plane": 0, "walkMask": 255}, {"x": 2, "y": 1, "plane": 0, "walkMask": 255}]}"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("b_bad.json"), r#"{"tiles": [{"x": "one", "y": 1, "plane": 0}]}"#).unwrap();
+        dir
+    }
+
+    #[test]
+    fn strict_failure_leaves_no_schema_and_can_be_rerun() {
+        let dir = good_and_bad_dir();
+        let db = dir.path().join("tiles.db");
+
+        let err = cmd_load_tiles(dir.path(), &db, &LoadOptions { strict: true, ..opts() }).unwrap_err();
+        assert!(err.to_string().contains("--strict"), "{err}");
+        assert_eq!(table_count(&db), 0);
+
+        cmd_load_tiles(dir.path(), &db, &opts()).unwrap();
+        assert_eq!(tile_count(&db), 2);
+        let index: i64 = Connection::open(&db)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name='idx_tiles_walkable'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn loads_a_gzipped_single_file() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("tiles.json.gz");
+        let mut gz = GzEncoder::new(File::create(&file).unwrap(), Compression::default());
+        gz.write_all(br#"{"tiles": [{"x": 5, "y": 6, "plane": 0, "walkMask": 3}]}"#).unwrap();
+        gz.finish().unwrap();
+        let db = dir.path().join("tiles.db");
+
+        cmd_load_tiles(&file, &db, &opts()).unwrap();
+        let mask: i64 = Connection::open(&db)
+            .unwrap()
+            .query_row("SELECT walk_mask FROM tiles WHERE x=5 AND y=6 AND plane=0", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(mask, 3);
+    }
+
+    #[test]
+    fn single_file_parse_error_fails_without_strict() {
+        let dir = good_and_bad_dir();
+        let db = dir.path().join("tiles.db");
+
+        let err = cmd_load_tiles(&dir.path().join("b_bad.json"), &db, &opts()).unwrap_err();
+        assert!(format!("{:#}", err).contains("b_bad.json"), "{err:#}");
+        assert_eq!(table_count(&db), 0);
+    }
+
+    fn overlapping_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.json"), r#"{"tiles": [{"x": 1, "y": 1, "plane": 0, "walkMask": 1}]}"#).unwrap();
+        fs::write(
+            dir.path().join("b.json"),
+            r#"{"tiles": [{"x": 1, "y": 1, "plane": 0, "walkMask": 1}, {"x": 2, "y": 1, "plane": 0, "walkMask": 1}]}"#,
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn overlapping_files_are_loaded_once_and_fail_under_strict() {
+        let dir = overlapping_dir();
+        let db = dir.path().join("tiles.db");
+
+        let err = cmd_load_tiles(dir.path(), &db, &LoadOptions { strict: true, ..opts() }).unwrap_err();
+        assert!(err.to_string().contains("1 duplicate tile(s)"), "{err}");
+        assert_eq!(table_count(&db), 0);
+
+        cmd_load_tiles(dir.path(), &db, &opts()).unwrap();
+        assert_eq!(tile_count(&db), 2);
+    }
+
+    #[test]
+    fn skip_bad_tiles_keeps_the_good_ones() {
+        let (res, rows) = stream(
+            r#"{"tiles": [{"x": 1, "y": 1, "plane": 0, "walkMask": 1}, {"x": "bad", "y": 1, "plane": 0},
+                          {"x": 3, "y": 1, "plane": 0, "walkMaskHex": "zz"}, {"x": 4, "y": 1, "plane": 0}]}"#,
+            true,
+        );
+        res.unwrap();
+        assert_eq!(rows.iter().map(|r| r.0).collect::<Vec<_>>(), vec![1, 4]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, rx) = mpsc::sync_channel::<FileBatch>(1);
+        let path = dir.path().join("tiles.json");
+        fs::write(&path, r#"{"tiles": [{"x": 1, "y": 1, "plane": 0}, {"y": 1}, {"y": 2}]}"#).unwrap();
+        let parse = ParseOptions { skip_bad_tiles: true, plane_offset: 0, batch_size: SUB_BATCH };
+        parse_file_and_stream(&path, &tx, parse).unwrap();
+        drop(tx);
+        let skipped: usize = rx.into_iter().map(|b| b.skipped_tiles).sum();
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn parses_hex_walk_masks() {
+        assert_eq!(parse_walk_mask_hex("0x0F").unwrap(), 0x0f);
+        assert_eq!(parse_walk_mask_hex(" 0Xff ").unwrap(), 0xff);
+        assert_eq!(parse_walk_mask_hex("a0").unwrap(), 0xa0);
+        assert!(parse_walk_mask_hex("0x100").is_err());
+        assert!(parse_walk_mask_hex("-1").is_err());
+        assert!(parse_walk_mask_hex("0xgg").is_err());
+
+        // walkMask wins over walkMaskHex, which wins over walkData.
+        let (res, rows) = stream(
+            r#"{"tiles": [{"x": 1, "y": 0, "plane": 0, "walkMask": 1, "walkMaskHex": "0x02"},
+                          {"x": 2, "y": 0, "plane": 0, "walkMaskHex": "0x02", "walkData": {"top": true}},
+                          {"x": 3, "y": 0, "plane": 0, "walkData": {"top": true}}]}"#,
+            false,
+        );
+        res.unwrap();
+        assert_eq!(rows.iter().map(|r| r.3).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(8)]);
+    }
+
+    #[test]
+    fn applies_json_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("tiles.json"),
+            r#"{"tiles": [{"x": 1, "y": 1, "plane": 0, "walkMask": 255}, {"x": 2, "y": 1, "plane": 0, "walkMask": 255}]}"#,
+        )
+        .unwrap();
+        let overrides = dir.path().join("overrides.json");
+        fs::write(
+            &overrides,
+            r#"[{"x": 1, "y": 1, "plane": 0, "blocked": true},
+                {"x": 2, "y": 1, "z": 0, "walk_data": {"NorthEast": true}},
+                {"x": 70, "y": 1, "plane": 0, "walkMask": 3}]"#,
+        )
+        .unwrap();
+        let db = dir.path().join("tiles.db");
+
+        cmd_load_tiles(&dir.path().join("tiles.json"), &db, &LoadOptions { overrides_path: Some(&overrides), ..opts() })
+            .unwrap();
+        let conn = Connection::open(&db).unwrap();
+        let mask = |x: i64| -> (i64, i64) {
+            conn.query_row("SELECT walk_mask, RegionID FROM tiles WHERE x=?1 AND y=1 AND plane=0", [x], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap()
+        };
+        assert_eq!(mask(1), (0, 0));
+        assert_eq!(mask(2), (1 << 7, 0));
+        assert_eq!(mask(70), (3, 1 << 8));
+    }
+
+    #[test]
+    fn rejects_json_overrides_without_a_mask() {
+        let dir = tempfile::tempdir().unwrap();
+        let overrides = dir.path().join("overrides.json");
+        fs::write(&overrides, r#"[{"x": 1, "y": 1, "plane": 0, "blocked": false}]"#).unwrap();
+        let err = read_json_overrides(&overrides).unwrap_err();
+        assert!(format!("{:#}", err).contains("blocked: false"), "{err:#}");
+    }
+
+    #[test]
+    fn streams_tiles_and_skips_other_keys() {
+        let (res, rows) = stream(
+            r#"{"chunk": {"x": 1, "z": 2}, "meta": [1, {"a": 2}],
+                "tiles": [{"x": 64, "y": 1, "plane": 0, "walkMask": 15}, {"x": 2, "y": 3, "plane": 1}]}"#,
+            false,
+        );
+        res.unwrap();
+        assert_eq!(rows, vec![(64, 1, 0, Some(15), 256), (2, 3, 1, None, 0)]);
+    }
+
+    /// `n` tiles along y = 0, optionally followed by broken JSON in place of the closing brackets.
+    fn many_tiles(n: i32, truncated: bool) -> String {
+        let tiles: Vec<String> = (0..n).map(|x| format!(r#"{{"x": {x}, "y": 0, "plane": 0, "walkMask": 255}}"#)).collect();
+        format!(r#"{{"tiles": [{}{}"#, tiles.join(", "), if truncated { ", {" } else { "]}" })
+    }
+
+    /// Streams `json` in batches of 10 through a rendezvous channel; returns the result and the size of
+    /// each batch received.
+    fn stream_batches(json: &str) -> (Result<()>, Vec<usize>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiles.json");
+        fs::write(&path, json).unwrap();
+        let (tx, rx) = mpsc::sync_channel::<FileBatch>(0);
+        let writer = thread::spawn(move || rx.into_iter().map(|b| b.tile_rows.len()).collect::<Vec<_>>());
+        let res = parse_file_and_stream(&path, &tx, ParseOptions { skip_bad_tiles: false, plane_offset: 0, batch_size: 10 });
+        drop(tx);
+        (res, writer.join().unwrap())
+    }
+
+    #[test]
+    fn large_files_stream_in_several_batches() {
+        let (res, batches) = stream_batches(&many_tiles(25, false));
+        res.unwrap();
+        assert_eq!(batches, vec![10, 10, 5]);
+    }
+
+    #[test]
+    fn full_batches_are_sent_before_the_end_of_the_file() {
+        // The file breaks after 25 tiles: the two full batches already went out while parsing,
+        // and the held-back partial batch is dropped with the error.
+        let (res, batches) = stream_batches(&many_tiles(25, true));
+        assert!(res.is_err());
+        assert_eq!(batches, vec![10, 10]);
+    }
+
+    #[test]
+    fn plane_offset_shifts_planes() {
+        let json = r#"{"tiles": [{"x": 1, "y": 1, "plane": 0}, {"x": 2, "y": 2, "plane": 3, "walkMask": 1}]}"#;
+        let (res, rows) = stream_shifted(json, false, 1);
+        res.unwrap();
+        assert_eq!(rows, vec![(1, 1, 1, None, 0), (2, 2, 4, Some(1), 0)]);
+    }
+
+    #[test]
+    fn negative_plane_after_offset_is_rejected_or_skipped() {
+        let json = r#"{"tiles": [{"x": 1, "y": 1, "plane": 0}, {"x": 2, "y": 2, "plane": 3}]}"#;
+        let (res, rows) = stream_shifted(json, false, -1);
+        assert!(format!("{:#}", res.unwrap_err()).contains("negative after --plane-offset -1"));
+        assert!(rows.is_empty());
+
+        let (res, rows) = stream_shifted(json, true, -1);
+        res.unwrap();
+        assert_eq!(rows, vec![(2, 2, 2, None, 0)]);
+    }
+
+    #[test]
+    fn rejects_trailing_data_without_sending_rows() {
+        let (res, rows) = stream(r#"{"tiles": [{"x": 1, "y": 1, "plane": 0}]} {"tiles": []}"#, false);
+        assert!(format!("{:#}", res.unwrap_err()).contains("trailing"));
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_second_tiles_key() {
+        let (res, rows) = stream(
+            r#"{"tiles": [{"x": 1, "y": 1, "plane": 0}], "tiles": [{"x": 2, "y": 2, "plane": 0}]}"#,
+            false,
+        );
+        assert!(format!("{:#}", res.unwrap_err()).contains("duplicate field `tiles`"));
+        assert!(rows.is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::Connection;
+use std::fs;
+use std::path::Path;
+
+/// Which side wins when both DBs have a row with the same primary key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergePrefer {
+    /// Whichever DB file was modified most recently
+    Newest,
+    /// Keep rows already in --into
+    Base,
+    /// Overwrite with rows from --from
+    Other,
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info('{}')", schema, table.replace('\'', "''")))?;
+    let cols = stmt
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(cols)
+}
+
+fn primary_key(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info('{}')", schema, table.replace('\'', "''")))?;
+    let mut pk = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(5)?, r.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    pk.retain(|(pos, _)| *pos > 0);
+    pk.sort();
+    Ok(pk.into_iter().map(|(_, name)| name).collect())
+}
+
+fn table_names(conn: &Connection, schema: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT name FROM {}.sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        schema
+    ))?;
+    let names = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(names)
+}
+
+fn other_is_newer(into: &Path, from: &Path) -> Result<bool> {
+    let modified = |p: &Path| -> Result<std::time::SystemTime> {
+        fs::metadata(p)
+            .and_then(|m| m.modified())
+            .with_context(|| format!("Read modification time of {}", p.display()))
+    };
+    Ok(modified(from)? > modified(into)?)
+}
+
+/// Merges `tiles` and the teleport tables of `from` into `into` in one transaction. Rows are matched
+/// on each table's primary key and conflicts resolved by `prefer`; only columns present in both
+/// DBs are copied, so a winning source row never clears columns that only `into` has. Tables missing from `into` and the `meta` table are left alone.
+pub fn cmd_merge_tiles(into: &Path, from: &Path, prefer: MergePrefer) -> Result<()> {
+    if !into.exists() {
+        bail!("Base DB not found: {}", into.display());
+    }
+    if !from.exists() {
+        bail!("Source DB not found: {}", from.display());
+    }
+
+    let other_wins = match prefer {
+        MergePrefer::Other => true,
+        MergePrefer::Base => false,
+        MergePrefer::Newest => other_is_newer(into, from)?,
+    };
+    println!(
+        "Merging {} into {} ({} rows win conflicts)",
+        from.display(),
+        into.display(),
+        if other_wins { "source" } else { "base" }
+    );
+
+    let mut conn = Connection::open(into).with_context(|| format!("Open DB {}", into.display()))?;
+    crate::db::ensure_supported_schema(&conn, into)?;
+    {
+        let other = Connection::open(from).with_context(|| format!("Open DB {}", from.display()))?;
+        crate::db::ensure_supported_schema(&other, from)?;
+    }
+    conn.execute("ATTACH DATABASE ?1 AS other", [from.to_string_lossy()])?;
+
+    let base_tables = table_names(&conn, "main")?;
+    let other_tables = table_names(&conn, "other")?;
+    if !other_tables.iter().any(|t| t == "tiles") {
+        bail!("{} has no tiles table", from.display());
+    }
+
+    let tx = conn.transaction()?;
+    for t in &other_tables {
+        if t == "meta" {
+            continue;
+        }
+        if !base_tables.contains(t) {
+            println!("Skipping `{}`: not present in {}", t, into.display());
+            continue;
+        }
+        let base_cols = table_columns(&tx, "main", t)?;
+        let cols: Vec<String> = table_columns(&tx, "other", t)?
+            .into_iter()
+            .filter(|c| base_cols.contains(c))
+            .collect();
+        if cols.is_empty() {
+            continue;
+        }
+        let before: i64 = tx.query_row(&format!("SELECT COUNT(*) FROM main.{}", t), [], |r| r.get(0))?;
+        let col_list = cols.join(", ");
+        let pk = primary_key(&tx, "main", t)?;
+        let sql = if other_wins && !pk.is_empty() && pk.iter().all(|c| cols.contains(c)) {
+            let updates: Vec<String> = cols
+                .iter()
+                .filter(|c| !pk.contains(c))
+                .map(|c| format!("{c} = excluded.{c}"))
+                .collect();
+            let action = if updates.is_empty() {
+                "NOTHING".to_string()
+            } else {
+                format!("UPDATE SET {}", updates.join(", "))
+            };
+            // `WHERE true` keeps SQLite from parsing ON CONFLICT as a join constraint.
+            format!(
+                "INSERT INTO main.{} ({}) SELECT {} FROM other.{} WHERE true ON CONFLICT ({}) DO {}",
+                t,
+                col_list,
+                col_list,
+                t,
+                pk.join(", "),
+                action
+            )
+        } else {
+            format!("INSERT OR IGNORE INTO main.{} ({}) SELECT {} FROM other.{}", t, col_list, col_list, t)
+        };
+        let changed = tx.execute(&sql, [])?;
+        let after: i64 = tx.query_row(&format!("SELECT COUNT(*) FROM main.{}", t), [], |r| r.get(0))?;
+        println!("  `{}`: {} rows written, {} new", t, changed, after - before);
+    }
+    tx.commit()?;
+    conn.execute("DETACH DATABASE other", [])?;
+
+    println!("Merge complete; output in {}", into.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::tiles_db;
+
+    fn other_db(path: &Path) {
+        // No RegionID column: the merge must not clear it in the base DB.
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tiles (x INTEGER, y INTEGER, plane INTEGER, walk_mask INTEGER, PRIMARY KEY (x, y, plane));
+             INSERT INTO tiles VALUES (0, 0, 0, 2), (1, 0, 0, 3);",
+        )
+        .unwrap();
+    }
+
+    fn rows(path: &Path) -> Vec<(i32, i64, Option<i64>)> {
+        let conn = Connection::open(path).unwrap();
+        let mut stmt = conn.prepare("SELECT x, walk_mask, RegionID FROM tiles ORDER BY x").unwrap();
+        let rows = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        rows
+    }
+
+    fn setup() -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let into = dir.path().join("into.db");
+        let from = dir.path().join("from.db");
+        let conn = tiles_db(&into, &[(0, 0, 0, Some(1))]);
+        conn.execute("UPDATE tiles SET RegionID = 7", []).unwrap();
+        other_db(&from);
+        (dir, into, from)
+    }
+
+    #[test]
+    fn prefer_other_updates_shared_columns_only() {
+        let (_dir, into, from) = setup();
+        cmd_merge_tiles(&into, &from, MergePrefer::Other).unwrap();
+        assert_eq!(rows(&into), vec![(0, 2, Some(7)), (1, 3, None)]);
+    }
+
+    #[test]
+    fn prefer_base_keeps_overlapping_rows() {
+        let (_dir, into, from) = setup();
+        cmd_merge_tiles(&into, &from, MergePrefer::Base).unwrap();
+        assert_eq!(rows(&into), vec![(0, 1, Some(7)), (1, 3, None)]);
+    }
+
+    fn set_age(path: &Path, secs_ago: u64) {
+        let t = std::time::SystemTime::now() - std::time::Duration::from_secs(secs_ago);
+        fs::File::options().write(true).open(path).unwrap().set_modified(t).unwrap();
+    }
+
+    #[test]
+    fn prefer_newest_follows_file_modification_times() {
+        let (_dir, into, from) = setup();
+        set_age(&into, 3600);
+        set_age(&from, 60);
+        cmd_merge_tiles(&into, &from, MergePrefer::Newest).unwrap();
+        assert_eq!(rows(&into), vec![(0, 2, Some(7)), (1, 3, None)]);
+
+        let (_dir, into, from) = setup();
+        set_age(&into, 60);
+        set_age(&from, 3600);
+        cmd_merge_tiles(&into, &from, MergePrefer::Newest).unwrap();
+        assert_eq!(rows(&into), vec![(0, 1, Some(7)), (1, 3, None)]);
+    }
+}
@@ -1,3 +1,7 @@
 pub mod load_tiles;
 pub mod import_xlsx;
 pub mod tile_cleaner;
+pub mod gen_world;
+pub mod density;
+pub mod merge_tiles;
+pub mod render;
\ No newline at end of file
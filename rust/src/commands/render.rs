@@ -0,0 +1,211 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::Path;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum RenderFormat {
+    Ascii,
+    Png,
+}
+
+/// ASCII output is meant for small areas pasted into issues; anything wider is unreadable.
+const MAX_ASCII_WIDTH: i64 = 256;
+
+/// Upper bound on width * height for any format. The grid plus the RGB buffer cost about 4 bytes
+/// per tile, so this keeps a render under ~256 MiB; a whole RS3 plane needs narrowing first.
+const MAX_RENDER_TILES: i64 = 1 << 26;
+
+#[cfg(feature = "png")]
+const WALKABLE_RGB: [u8; 3] = [0xE8, 0xE4, 0xD8];
+#[cfg(feature = "png")]
+const BLOCKED_RGB: [u8; 3] = [0x40, 0x40, 0x48];
+#[cfg(feature = "png")]
+const MISSING_RGB: [u8; 3] = [0x00, 0x00, 0x00];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Missing,
+    Blocked,
+    Walkable,
+}
+
+/// Inclusive tile bounds of the area to render; unset sides default to the plane's extent.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderBounds {
+    pub min_x: Option<i32>,
+    pub max_x: Option<i32>,
+    pub min_y: Option<i32>,
+    pub max_y: Option<i32>,
+}
+
+/// Renders one plane as a walkability map: walkable tiles (non-zero walk_mask) in one colour,
+/// blocked tiles in another, and coordinates without a tile row left empty. North (+y) is up.
+pub fn cmd_render(db_path: &Path, plane: i32, bounds: RenderBounds, format: RenderFormat, out: Option<&Path>) -> Result<()> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open DB at {}", db_path.display()))?;
+
+    let extent: (Option<i32>, Option<i32>, Option<i32>, Option<i32>) = conn.query_row(
+        "SELECT MIN(x), MAX(x), MIN(y), MAX(y) FROM tiles WHERE plane = ?1",
+        [plane],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )?;
+    let (Some(ext_min_x), Some(ext_max_x), Some(ext_min_y), Some(ext_max_y)) = extent else {
+        bail!("No tiles on plane {} in {}", plane, db_path.display());
+    };
+    let min_x = bounds.min_x.unwrap_or(ext_min_x);
+    let max_x = bounds.max_x.unwrap_or(ext_max_x);
+    let min_y = bounds.min_y.unwrap_or(ext_min_y);
+    let max_y = bounds.max_y.unwrap_or(ext_max_y);
+    if min_x > max_x || min_y > max_y {
+        bail!("Empty render area x {}..={}, y {}..={}", min_x, max_x, min_y, max_y);
+    }
+    let width = max_x as i64 - min_x as i64 + 1;
+    let height = max_y as i64 - min_y as i64 + 1;
+    if matches!(format, RenderFormat::Ascii) && width > MAX_ASCII_WIDTH {
+        bail!(
+            "Area is {} tiles wide; ASCII output is limited to {} (narrow it with --min-x/--max-x or use --format png)",
+            width,
+            MAX_ASCII_WIDTH
+        );
+    }
+    if width * height > MAX_RENDER_TILES {
+        bail!(
+            "Area is {}x{} tiles; rendering is limited to {} tiles (narrow it with --min-x/--max-x/--min-y/--max-y)",
+            width,
+            height,
+            MAX_RENDER_TILES
+        );
+    }
+    let (width, height) = (width as usize, height as usize);
+
+    // Row 0 is the northernmost row so the output reads like a map.
+    let mut grid = vec![Cell::Missing; width * height];
+    let mut stmt = conn.prepare(
+        "SELECT x, y, walk_mask FROM tiles WHERE plane = ?1 AND x BETWEEN ?2 AND ?3 AND y BETWEEN ?4 AND ?5",
+    )?;
+    let mut rows = stmt.query(params![plane, min_x, max_x, min_y, max_y])?;
+    while let Some(r) = rows.next()? {
+        let x: i32 = r.get(0)?;
+        let y: i32 = r.get(1)?;
+        let mask: Option<i64> = r.get(2)?;
+        let col = (x - min_x) as usize;
+        let row = (max_y - y) as usize;
+        grid[row * width + col] = if mask.unwrap_or(0) != 0 { Cell::Walkable } else { Cell::Blocked };
+    }
+
+    match format {
+        RenderFormat::Ascii => {
+            let mut text = String::with_capacity((width + 1) * height);
+            for row in grid.chunks(width) {
+                text.extend(row.iter().map(|c| match c {
+                    Cell::Walkable => '.',
+                    Cell::Blocked => '#',
+                    Cell::Missing => ' ',
+                }));
+                text.push('\n');
+            }
+            match out {
+                Some(p) => std::fs::write(p, text).with_context(|| format!("Failed to write {}", p.display()))?,
+                None => print!("{}", text),
+            }
+        }
+        RenderFormat::Png => {
+            let Some(p) = out else {
+                bail!("--format png needs --out");
+            };
+            write_png(p, width as u32, height as u32, &grid)?;
+            println!(
+                "Rendered plane {} x {}..={}, y {}..={} ({}x{}) to {}",
+                plane,
+                min_x,
+                max_x,
+                min_y,
+                max_y,
+                width,
+                height,
+                p.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "png")]
+fn write_png(path: &Path, width: u32, height: u32, grid: &[Cell]) -> Result<()> {
+    let mut pixels = Vec::with_capacity(grid.len() * 3);
+    for c in grid {
+        pixels.extend_from_slice(match c {
+            Cell::Walkable => &WALKABLE_RGB,
+            Cell::Blocked => &BLOCKED_RGB,
+            Cell::Missing => &MISSING_RGB,
+        });
+    }
+    let img = image::RgbImage::from_raw(width, height, pixels).context("pixel buffer does not match the render size")?;
+    img.save_with_format(path, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(not(feature = "png"))]
+fn write_png(_path: &Path, _width: u32, _height: u32, _grid: &[Cell]) -> Result<()> {
+    bail!("--format png needs a build with the `png` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch, tiles_db, OPEN};
+
+    /// x 0..=2, y 0..=1 on plane 0 with two gaps; (0, 1) has a NULL mask.
+    fn small_db(path: &Path) {
+        tiles_db(path, &[(0, 0, 0, Some(OPEN)), (1, 0, 0, Some(0)), (0, 1, 0, None), (2, 1, 0, Some(OPEN)), (5, 5, 1, Some(OPEN))]);
+    }
+
+    #[test]
+    fn ascii_grid_puts_north_first() {
+        let (dir, db) = scratch("render.db");
+        small_db(&db);
+        let out = dir.path().join("map.txt");
+
+        cmd_render(&db, 0, RenderBounds::default(), RenderFormat::Ascii, Some(&out)).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "# .\n.# \n");
+
+        let bounds = RenderBounds { min_x: Some(-1), max_x: Some(1), min_y: Some(0), max_y: Some(0) };
+        cmd_render(&db, 0, bounds, RenderFormat::Ascii, Some(&out)).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), " .#\n");
+    }
+
+    #[test]
+    fn oversized_areas_are_rejected() {
+        let (dir, db) = scratch("render.db");
+        small_db(&db);
+        let out = dir.path().join("map.png");
+
+        let bounds = RenderBounds { min_x: Some(0), max_x: Some(9_999), min_y: Some(0), max_y: Some(9_999) };
+        let err = cmd_render(&db, 0, bounds, RenderFormat::Png, Some(&out)).unwrap_err();
+        assert!(err.to_string().contains("rendering is limited to"), "{err}");
+        let bounds = RenderBounds { min_x: Some(i32::MIN), max_x: Some(i32::MAX), ..bounds };
+        assert!(cmd_render(&db, 0, bounds, RenderFormat::Png, Some(&out)).is_err());
+        assert!(!out.exists());
+
+        let bounds = RenderBounds { min_x: Some(0), max_x: Some(MAX_ASCII_WIDTH as i32), ..Default::default() };
+        let err = cmd_render(&db, 0, bounds, RenderFormat::Ascii, None).unwrap_err();
+        assert!(err.to_string().contains("ASCII output is limited"), "{err}");
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_pixels_match_the_grid() {
+        let (dir, db) = scratch("render.db");
+        small_db(&db);
+        let out = dir.path().join("map.png");
+
+        cmd_render(&db, 0, RenderBounds::default(), RenderFormat::Png, Some(&out)).unwrap();
+        let img = image::open(&out).unwrap().into_rgb8();
+        assert_eq!(img.dimensions(), (3, 2));
+        assert_eq!(img.get_pixel(0, 0).0, BLOCKED_RGB);
+        assert_eq!(img.get_pixel(1, 0).0, MISSING_RGB);
+        assert_eq!(img.get_pixel(2, 0).0, WALKABLE_RGB);
+        assert_eq!(img.get_pixel(0, 1).0, WALKABLE_RGB);
+        assert_eq!(img.get_pixel(1, 1).0, BLOCKED_RGB);
+    }
+}
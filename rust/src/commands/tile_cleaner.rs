@@ -1,6 +1,7 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension, Row};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::Path;
 use std::sync::mpsc;
@@ -8,89 +9,12 @@ use std::thread;
 
 use rayon::prelude::*;
 
-type Tile = (i32, i32, i32);
-
-#[derive(Clone, Copy, Debug, Default)]
-struct WalkMaskOverride {
-    force_mask: Option<i64>,
-    or_mask: i64,
-}
-
-fn dir_to_bit(dir: &str) -> Option<i64> {
-    match dir {
-        "left" => Some(1 << 0),
-        "bottom" => Some(1 << 1),
-        "right" => Some(1 << 2),
-        "top" => Some(1 << 3),
-        "topleft" => Some(1 << 4),
-        "bottomleft" => Some(1 << 5),
-        "bottomright" => Some(1 << 6),
-        "topright" => Some(1 << 7),
-        _ => None,
-    }
-}
-
-fn diag_required_dirs(dir: &str) -> Option<[&'static str; 2]> {
-    match dir {
-        "topleft" => Some(["top", "left"]),
-        "topright" => Some(["top", "right"]),
-        "bottomleft" => Some(["bottom", "left"]),
-        "bottomright" => Some(["bottom", "right"]),
-        _ => None,
-    }
-}
-
-fn build_fairy_ring_overrides(conn: &Connection) -> Result<std::sync::Arc<HashMap<Tile, WalkMaskOverride>>> {
-    let mut out: HashMap<Tile, WalkMaskOverride> = HashMap::new();
+use crate::db::{with_lock_hint, with_lock_hints};
+use crate::models::TileId;
+use crate::navmesh::{build_fairy_ring_overrides, key_delta, neighbors_from_reconciled, WalkCache, WalkMaskOverride};
 
-    let mut stmt = match conn.prepare("SELECT x, y, plane FROM teleports_fairy_rings_nodes") {
-        Ok(s) => s,
-        Err(_) => return Ok(std::sync::Arc::new(out)),
-    };
-
-    let mut rows = stmt.query([])?;
-    while let Some(r) = rows.next()? {
-        let x: Option<i64> = r.get(0)?;
-        let y: Option<i64> = r.get(1)?;
-        let p: Option<i64> = r.get(2)?;
-        let (Some(x), Some(y), Some(p)) = (x, y, p) else { continue; };
-        let ring: Tile = (x as i32, y as i32, p as i32);
-
-        out.entry(ring)
-            .and_modify(|e| e.force_mask = Some(255))
-            .or_insert(WalkMaskOverride { force_mask: Some(255), or_mask: 0 });
-
-        let neighbors: [((i32, i32), &str); 8] = [
-            ((-1, 0), "right"),
-            ((1, 0), "left"),
-            ((0, -1), "top"),
-            ((0, 1), "bottom"),
-            ((-1, -1), "topright"),
-            ((1, -1), "topleft"),
-            ((-1, 1), "bottomright"),
-            ((1, 1), "bottomleft"),
-        ];
-
-        for &((dx, dy), dir) in &neighbors {
-            let nt: Tile = (ring.0 + dx, ring.1 + dy, ring.2);
-            let mut bits = dir_to_bit(dir).unwrap_or(0);
-            if let Some(req) = diag_required_dirs(dir) {
-                for d in req {
-                    bits |= dir_to_bit(d).unwrap_or(0);
-                }
-            }
-            out.entry(nt)
-                .and_modify(|e| {
-                    if e.force_mask.is_none() {
-                        e.or_mask |= bits;
-                    }
-                })
-                .or_insert(WalkMaskOverride { force_mask: None, or_mask: bits });
-        }
-    }
-
-    Ok(std::sync::Arc::new(out))
-}
+type Tile = (i32, i32, i32);
+type TransitionMap = HashMap<Tile, Vec<Tile>>;
 
 fn center_tile(min_x: i32, max_x: i32, min_y: i32, max_y: i32, plane: i32) -> Tile {
     let (min_x, max_x) = if min_x <= max_x { (min_x, max_x) } else { (max_x, min_x) };
@@ -100,182 +24,6 @@ fn center_tile(min_x: i32, max_x: i32, min_y: i32, max_y: i32, plane: i32) -> Ti
     (cx as i32, cy as i32, plane)
 }
 
-const RECIP: &[(&str, &str)] = &[
-    ("left", "right"),
-    ("right", "left"),
-    ("top", "bottom"),
-    ("bottom", "top"),
-    ("topleft", "bottomright"),
-    ("topright", "bottomleft"),
-    ("bottomleft", "topright"),
-    ("bottomright", "topleft"),
-];
-
-fn key_delta(k: &str) -> Option<(i32, i32, i32)> {
-    match k {
-        "top" => Some((0, 1, 0)),
-        "bottom" => Some((0, -1, 0)),
-        "right" => Some((1, 0, 0)),
-        "left" => Some((-1, 0, 0)),
-        "topright" => Some((1, 1, 0)),
-        "topleft" => Some((-1, 1, 0)),
-        "bottomright" => Some((1, -1, 0)),
-        "bottomleft" => Some((-1, -1, 0)),
-        _ => None,
-    }
-}
-
-fn diag_require(k: &str) -> Option<(&'static str, &'static str)> {
-    match k {
-        "topleft" => Some(("top", "left")),
-        "topright" => Some(("top", "right")),
-        "bottomleft" => Some(("bottom", "left")),
-        "bottomright" => Some(("bottom", "right")),
-        _ => None,
-    }
-}
-
-struct WalkCache {
-    raw: HashMap<Tile, HashMap<String, bool>>, 
-    reconciled: HashMap<Tile, HashMap<String, bool>>, 
-    overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
-}
-
-impl WalkCache {
-    fn new() -> Self {
-        Self {
-            raw: HashMap::new(),
-            reconciled: HashMap::new(),
-            overrides: std::sync::Arc::new(HashMap::new()),
-        }
-    }
-
-    fn new_with_overrides(overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>) -> Self {
-        Self {
-            raw: HashMap::new(),
-            reconciled: HashMap::new(),
-            overrides,
-        }
-    }
-
-    // Mapping order for walk_mask bits: 0..7
-    // [left, bottom, right, top, topleft, bottomleft, bottomright, topright]
-    fn mask_dirs() -> [&'static str; 8] {
-        [
-            "left",
-            "bottom",
-            "right",
-            "top",
-            "topleft",
-            "bottomleft",
-            "bottomright",
-            "topright",
-        ]
-    }
-
-    fn decode_mask(mask: i64) -> HashMap<String, bool> {
-        let mut out = HashMap::new();
-        let dirs = Self::mask_dirs();
-        for i in 0..8 {
-            if (mask & (1 << i)) != 0 {
-                out.insert(dirs[i].to_string(), true);
-            }
-        }
-        out
-    }
-
-    fn encode_mask(map: &HashMap<String, bool>) -> i64 {
-        let mut mask: i64 = 0;
-        let dirs = Self::mask_dirs();
-        for i in 0..8 {
-            if map.get(dirs[i]).copied().unwrap_or(false) { mask |= 1 << i; }
-        }
-        mask
-    }
-
-    fn get_raw(&mut self, conn: &Connection, t: Tile) -> Result<HashMap<String, bool>> {
-        if let Some(m) = self.raw.get(&t) { return Ok(m.clone()); }
-        let (x, y, p) = t;
-        let row: Option<Option<i64>> = conn
-            .query_row(
-                "SELECT walk_mask FROM tiles WHERE x=?1 AND y=?2 AND plane=?3",
-                params![x, y, p],
-                |row| Ok(row.get(0)?),
-            )
-            .optional()?;
-
-        let Some(walk_mask) = row else {
-            // Tile row does not exist; never allow overrides to "create" walkability for missing tiles.
-            self.raw.insert(t, HashMap::new());
-            return Ok(HashMap::new());
-        };
-
-        let mut w = walk_mask.unwrap_or(0);
-        if let Some(ov) = self.overrides.get(&t) {
-            if let Some(f) = ov.force_mask {
-                w = f;
-            } else {
-                w |= ov.or_mask;
-            }
-        }
-
-        let m = if w != 0 { Self::decode_mask(w) } else { HashMap::new() };
-        self.raw.insert(t, m.clone());
-        Ok(m)
-    }
-
-    fn get_reconciled(&mut self, conn: &Connection, t: Tile) -> Result<HashMap<String, bool>> {
-        if let Some(m) = self.reconciled.get(&t) { return Ok(m.clone()); }
-        let mut base = self.get_raw(conn, t)?;
-        if base.is_empty() {
-            self.reconciled.insert(t, HashMap::new());
-            return Ok(HashMap::new());
-        }
-        let (tx, ty, tp) = t;
-        
-        for key in ["left", "right", "top", "bottom"] {
-            if !base.get(key).copied().unwrap_or(false) { continue; }
-            if let Some((dx, dy, dp)) = key_delta(key) {
-                let n = (tx + dx, ty + dy, tp + dp);
-                let nwalk = self.get_raw(conn, n)?;
-                let recip = RECIP.iter().find(|(a, _)| *a == key).map(|(_, b)| *b).unwrap();
-                let nrecip = nwalk.get(recip).copied().unwrap_or(false);
-                if !nrecip { base.insert(key.to_string(), false); }
-            }
-        }
-        for key in ["topleft", "topright", "bottomleft", "bottomright"] {
-            if !base.get(key).copied().unwrap_or(false) { continue; }
-            if let Some((r1, r2)) = diag_require(key) {
-                if !(base.get(r1).copied().unwrap_or(false) && base.get(r2).copied().unwrap_or(false)) {
-                    base.insert(key.to_string(), false);
-                    continue;
-                }
-            }
-            if let Some((dx, dy, dp)) = key_delta(key) {
-                let n = (tx + dx, ty + dy, tp + dp);
-                let nwalk = self.get_raw(conn, n)?;
-                let recip = RECIP.iter().find(|(a, _)| *a == key).map(|(_, b)| *b).unwrap();
-                let nrecip = nwalk.get(recip).copied().unwrap_or(false);
-                if !nrecip { base.insert(key.to_string(), false); }
-            }
-        }
-        self.reconciled.insert(t, base.clone());
-        Ok(base)
-    }
-}
-
-fn neighbors_from_reconciled(map: &HashMap<String, bool>, t: Tile) -> Vec<Tile> {
-    let (x, y, p) = t;
-    let mut out = Vec::new();
-    for (k, allowed) in map.iter() {
-        if !*allowed { continue; }
-        if let Some((dx, dy, dp)) = key_delta(k) {
-            out.push((x + dx, y + dy, p + dp));
-        }
-    }
-    out
-}
-
 fn get_door_links(conn: &Connection) -> Result<HashMap<Tile, Vec<Tile>>> {
     let mut adj: HashMap<Tile, Vec<Tile>> = HashMap::new();
     let mut stmt = conn.prepare(
@@ -415,11 +163,21 @@ fn get_item_dest_tiles(conn: &Connection) -> Result<Vec<Tile>> {
     Ok(out)
 }
 
-fn get_ifslot_dest_tiles(conn: &Connection) -> Result<Vec<Tile>> {
-    let mut out: Vec<Tile> = Vec::new();
-    let mut stmt = conn.prepare(
-        "SELECT dest_min_x, dest_max_x, dest_min_y, dest_max_y, dest_plane FROM teleports_ifslot_nodes",
-    )?;
+/// Interface-slot teleports split into globally usable destinations and origin-gated ones.
+/// Rows with a full `orig_*` box only fire from that origin; older DBs without the columns treat
+/// every row as global.
+fn get_ifslot_transitions(conn: &Connection) -> Result<(Vec<Tile>, TransitionMap)> {
+    let mut global: Vec<Tile> = Vec::new();
+    let mut gated: TransitionMap = HashMap::new();
+    let has_origin = get_table_columns(conn, "teleports_ifslot_nodes")?
+        .iter()
+        .any(|c| c == "orig_min_x");
+    let sql = if has_origin {
+        "SELECT dest_min_x, dest_max_x, dest_min_y, dest_max_y, dest_plane, orig_min_x, orig_max_x, orig_min_y, orig_max_y, orig_plane FROM teleports_ifslot_nodes"
+    } else {
+        "SELECT dest_min_x, dest_max_x, dest_min_y, dest_max_y, dest_plane, NULL, NULL, NULL, NULL, NULL FROM teleports_ifslot_nodes"
+    };
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([])?;
     while let Some(r) = rows.next()? {
         let d_min_x: Option<i64> = r.get(0)?;
@@ -435,67 +193,187 @@ fn get_ifslot_dest_tiles(conn: &Connection) -> Result<Vec<Tile>> {
             d_max_y.unwrap() as i32,
             d_plane.unwrap() as i32,
         );
-        out.push(center_tile(d_min_x, d_max_x, d_min_y, d_max_y, d_plane));
+        let dest = center_tile(d_min_x, d_max_x, d_min_y, d_max_y, d_plane);
+
+        let o_min_x: Option<i64> = r.get(5)?;
+        let o_max_x: Option<i64> = r.get(6)?;
+        let o_min_y: Option<i64> = r.get(7)?;
+        let o_max_y: Option<i64> = r.get(8)?;
+        let o_plane: Option<i64> = r.get(9)?;
+        match (o_min_x, o_max_x, o_min_y, o_max_y, o_plane) {
+            (Some(a), Some(b), Some(c), Some(d), Some(p)) => {
+                let origin = center_tile(a as i32, b as i32, c as i32, d as i32, p as i32);
+                gated.entry(origin).or_default().push(dest);
+            }
+            _ => global.push(dest),
+        }
     }
-    Ok(out)
+    Ok((global, gated))
+}
+
+/// Teleport families the search may use; matches the `kind` column of the `teleports_all` view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TeleportKind {
+    Door,
+    Lodestone,
+    Object,
+    Npc,
+    Item,
+    Ifslot,
+}
+
+impl TeleportKind {
+    pub const ALL: &'static [TeleportKind] = &[
+        TeleportKind::Door,
+        TeleportKind::Lodestone,
+        TeleportKind::Object,
+        TeleportKind::Npc,
+        TeleportKind::Item,
+        TeleportKind::Ifslot,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TeleportKind::Door => "door",
+            TeleportKind::Lodestone => "lodestone",
+            TeleportKind::Object => "object",
+            TeleportKind::Npc => "npc",
+            TeleportKind::Item => "item",
+            TeleportKind::Ifslot => "ifslot",
+        }
+    }
+}
+
+/// Non-walking movement available to the reachability search, loaded from the teleport tables.
+struct Transitions {
+    door: HashMap<Tile, Vec<Tile>>,
+    lode_set: HashSet<Tile>,
+    lodestones: Vec<Tile>,
+    obj: HashMap<Tile, Vec<Tile>>,
+    npc: HashMap<Tile, Vec<Tile>>,
+    item_dests: Vec<Tile>,
+    ifslot: Vec<Tile>,
+    ifslot_gated: TransitionMap,
+}
+
+impl Transitions {
+    /// Loads the transitions of the given kinds; the rest stay empty.
+    fn load(conn: &Connection, kinds: &[TeleportKind]) -> Result<Self> {
+        let mut tr = Self {
+            door: HashMap::new(),
+            lode_set: HashSet::new(),
+            lodestones: Vec::new(),
+            obj: HashMap::new(),
+            npc: HashMap::new(),
+            item_dests: Vec::new(),
+            ifslot: Vec::new(),
+            ifslot_gated: HashMap::new(),
+        };
+        for kind in TeleportKind::ALL.iter().filter(|k| !kinds.contains(k)) {
+            println!("Skipping {} teleports (excluded by --teleport-kinds)", kind.as_str());
+        }
+        if kinds.contains(&TeleportKind::Door) {
+            println!("Loading door links...");
+            tr.door = get_door_links(conn)?;
+            println!("Loaded {} door link origins with {} total destinations", tr.door.len(), tr.door.values().map(|v| v.len()).sum::<usize>());
+        }
+        if kinds.contains(&TeleportKind::Lodestone) {
+            println!("Loading lodestones...");
+            (tr.lode_set, tr.lodestones) = get_lodestones(conn)?;
+            println!("Loaded {} lodestone destinations", tr.lodestones.len());
+        }
+        if kinds.contains(&TeleportKind::Object) {
+            println!("Loading object transitions...");
+            tr.obj = get_object_transitions(conn)?;
+            println!("Loaded {} object transition origins with {} total destinations", tr.obj.len(), tr.obj.values().map(|v| v.len()).sum::<usize>());
+        }
+        if kinds.contains(&TeleportKind::Npc) {
+            println!("Loading NPC transitions...");
+            tr.npc = get_npc_transitions(conn)?;
+            println!("Loaded {} NPC transition origins with {} total destinations", tr.npc.len(), tr.npc.values().map(|v| v.len()).sum::<usize>());
+        }
+        if kinds.contains(&TeleportKind::Item) {
+            println!("Loading item teleport destinations...");
+            tr.item_dests = get_item_dest_tiles(conn)?;
+            println!("Loaded {} item teleport destinations", tr.item_dests.len());
+        }
+        if kinds.contains(&TeleportKind::Ifslot) {
+            println!("Loading interface slot destinations...");
+            (tr.ifslot, tr.ifslot_gated) = get_ifslot_transitions(conn)?;
+            println!(
+                "Loaded {} global and {} origin-gated interface slot destinations",
+                tr.ifslot.len(),
+                tr.ifslot_gated.values().map(|v| v.len()).sum::<usize>()
+            );
+        }
+
+        Ok(tr)
+    }
+}
+
+// Packs a tile for a visited set. A tile outside the `TileId` range (e.g. a negative coordinate in
+// the source DB or a teleport row) fails the run rather than silently dropping out of the search.
+fn tile_id(t: Tile) -> Result<TileId> {
+    TileId::pack(t.0, t.1, t.2).ok_or_else(|| {
+        anyhow!(
+            "Tile {:?} is outside the supported coordinate range (x/y 0..={}, plane 0..={})",
+            t,
+            TileId::MAX_COORD,
+            TileId::MAX_PLANE
+        )
+    })
+}
+
+// Records a tile in the packed visited set; true if it was not visited before.
+fn mark_visited(vis: &mut HashSet<u64>, t: Tile) -> Result<bool> {
+    Ok(vis.insert(tile_id(t)?.0))
+}
+
+fn is_reachable(reachable: &HashSet<u64>, t: Tile) -> bool {
+    TileId::pack(t.0, t.1, t.2).is_some_and(|id| reachable.contains(&id.0))
 }
 
 fn reachable_tiles(
     conn: &Connection,
     start: Tile,
     overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
-) -> Result<HashSet<Tile>> {
-    println!("Loading door links...");
-    let door = get_door_links(conn)?;
-    println!("Loaded {} door link origins with {} total destinations", door.len(), door.values().map(|v| v.len()).sum::<usize>());
-    println!("Loading lodestones...");
-    let (lode_set, lodestones) = get_lodestones(conn)?;
-    println!("Loaded {} lodestone destinations", lodestones.len());
-    println!("Loading object transitions...");
-    let obj = get_object_transitions(conn)?;
-    println!("Loaded {} object transition origins with {} total destinations", obj.len(), obj.values().map(|v| v.len()).sum::<usize>());
-    println!("Loading NPC transitions...");
-    let npc = get_npc_transitions(conn)?;
-    println!("Loaded {} NPC transition origins with {} total destinations", npc.len(), npc.values().map(|v| v.len()).sum::<usize>());
-    println!("Loading item teleport destinations...");
-    let item_dests = get_item_dest_tiles(conn)?;
-    println!("Loaded {} item teleport destinations", item_dests.len());
-    println!("Loading interface slot destinations...");
-    let ifslot = get_ifslot_dest_tiles(conn)?;
-    println!("Loaded {} interface slot destinations", ifslot.len());
+    kinds: &[TeleportKind],
+) -> Result<HashSet<u64>> {
+    let Transitions { door, lode_set, lodestones, obj, npc, item_dests, ifslot, ifslot_gated } =
+        Transitions::load(conn, kinds)?;
 
     let mut cache = WalkCache::new_with_overrides(overrides.clone());
     let mut q: VecDeque<Tile> = VecDeque::new();
-    let mut vis: HashSet<Tile> = HashSet::new();
+    let mut vis: HashSet<u64> = HashSet::new();
 
     q.push_back(start);
-    vis.insert(start);
+    mark_visited(&mut vis, start)?;
 
     // Important: seed BFS with teleport endpoints so destination tiles are retained in the cleaned DB
     // even if their origin tiles are not walk-reachable (e.g., one-way walk masks or data issues).
     println!("Seeding BFS with teleport endpoints (door/lodestone/object/npc)...");
     for &n in door.values().flatten() {
-        if vis.insert(n) { q.push_back(n); }
+        if mark_visited(&mut vis, n)? { q.push_back(n); }
     }
     for &n in &lodestones {
-        if vis.insert(n) { q.push_back(n); }
+        if mark_visited(&mut vis, n)? { q.push_back(n); }
     }
     for &n in obj.values().flatten() {
-        if vis.insert(n) { q.push_back(n); }
+        if mark_visited(&mut vis, n)? { q.push_back(n); }
     }
     for &n in npc.values().flatten() {
-        if vis.insert(n) { q.push_back(n); }
+        if mark_visited(&mut vis, n)? { q.push_back(n); }
     }
 
     println!("Seeding BFS with fairy ring tiles...");
     for (&t, ov) in overrides.iter() {
         if ov.force_mask.is_some() {
-            if vis.insert(t) { q.push_back(t); }
+            if mark_visited(&mut vis, t)? { q.push_back(t); }
         }
     }
 
     for &n in &item_dests {
-        if vis.insert(n) { q.push_back(n); }
+        if mark_visited(&mut vis, n)? { q.push_back(n); }
     }
 
     let mut ifslot_enqueued = false;
@@ -510,34 +388,40 @@ fn reachable_tiles(
         }
         let rec = cache.get_reconciled(conn, t)?;
         for n in neighbors_from_reconciled(&rec, t) {
-            if vis.insert(n) { q.push_back(n); }
+            if mark_visited(&mut vis, n)? { q.push_back(n); }
         }
         if let Some(v) = door.get(&t) {
             for &n in v {
-                if vis.insert(n) { q.push_back(n); }
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
             }
         }
         if lode_set.contains(&t) {
             for &n in &lodestones {
-                if vis.insert(n) { q.push_back(n); }
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
             }
         }
         if let Some(v) = obj.get(&t) {
             for &n in v {
-                if vis.insert(n) { q.push_back(n); }
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
             }
         }
         if let Some(v) = npc.get(&t) {
             for &n in v {
-                if vis.insert(n) { q.push_back(n); }
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
             }
         }
         if !ifslot_enqueued && !ifslot.is_empty() {
             for &n in &ifslot {
-                if vis.insert(n) { q.push_back(n); }
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
             }
             ifslot_enqueued = true;
         }
+        // Origin-gated interface teleports only open up once their origin tile is reached.
+        if let Some(v) = ifslot_gated.get(&t) {
+            for &n in v {
+                if mark_visited(&mut vis, n)? { q.push_back(n); }
+            }
+        }
     }
 
     println!("Finished BFS; processed {} tiles with {} reachable tiles discovered", processed, vis.len());
@@ -545,6 +429,165 @@ fn reachable_tiles(
     Ok(vis)
 }
 
+/// Transition destinations usable from `t`: door/object/NPC/origin-gated interface links from
+/// their origin, lodestones from any lodestone, and (when `from_start`) the globally usable
+/// lodestone, item and ungated interface-slot teleports.
+fn transition_targets(tr: &Transitions, t: Tile, from_start: bool) -> Vec<Tile> {
+    let mut out = Vec::new();
+    for links in [&tr.door, &tr.obj, &tr.npc, &tr.ifslot_gated] {
+        if let Some(v) = links.get(&t) {
+            out.extend_from_slice(v);
+        }
+    }
+    if tr.lode_set.contains(&t) || from_start {
+        out.extend_from_slice(&tr.lodestones);
+    }
+    if from_start {
+        out.extend_from_slice(&tr.item_dests);
+        out.extend_from_slice(&tr.ifslot);
+    }
+    out
+}
+
+/// Depth-bounded variant of `reachable_tiles`: keeps tiles within `max_depth` steps of `start`,
+/// where a walking step (cardinal or diagonal) costs 1 and a transition costs `teleport_cost`.
+/// Teleport endpoints are not seeded up front, so only transitions actually reached count.
+fn reachable_tiles_within(
+    conn: &Connection,
+    start: Tile,
+    overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
+    kinds: &[TeleportKind],
+    max_depth: u32,
+    teleport_cost: u32,
+) -> Result<HashSet<u64>> {
+    let start_id = tile_id(start)?;
+    let tr = Transitions::load(conn, kinds)?;
+    let mut cache = WalkCache::new_with_overrides(overrides);
+    // Depth per visited tile; transitions may cost more than a step, so this is a Dijkstra.
+    let mut depth: HashMap<u64, u32> = HashMap::new();
+    let mut open: BinaryHeap<Reverse<(u32, u64)>> = BinaryHeap::new();
+    depth.insert(start_id.0, 0);
+    open.push(Reverse((0, start_id.0)));
+
+    println!("Starting depth-limited search from tile {:?} (max depth {})", start, max_depth);
+    let mut processed = 0usize;
+    while let Some(Reverse((d, id))) = open.pop() {
+        if depth.get(&id).is_some_and(|&b| d > b) {
+            continue;
+        }
+        processed += 1;
+        if processed.is_multiple_of(10000) {
+            println!("Processed {} tiles so far; open set {}", processed, open.len());
+        }
+        let t = TileId(id).unpack();
+        let rec = cache.get_reconciled(conn, t)?;
+        let walks = neighbors_from_reconciled(&rec, t).into_iter().map(|n| (n, 1));
+        let hops = transition_targets(&tr, t, id == start_id.0).into_iter().map(|n| (n, teleport_cost));
+        for (n, cost) in walks.chain(hops) {
+            let nd = d.saturating_add(cost);
+            if nd > max_depth {
+                continue;
+            }
+            let nid = tile_id(n)?;
+            if depth.get(&nid.0).is_some_and(|&b| nd >= b) {
+                continue;
+            }
+            depth.insert(nid.0, nd);
+            open.push(Reverse((nd, nid.0)));
+        }
+    }
+
+    println!("Finished depth-limited search; {} tiles within depth {}", depth.len(), max_depth);
+    Ok(depth.into_keys().collect())
+}
+
+// Costs for the goal-directed search, in tenths of a tile so diagonals stay integral.
+const STEP_COST: i64 = 10;
+const DIAG_COST: i64 = 14;
+const TRANSITION_COST: i64 = 10;
+
+fn octile_distance(a: Tile, b: Tile) -> i64 {
+    let dx = (a.0 - b.0).unsigned_abs() as i64;
+    let dy = (a.1 - b.1).unsigned_abs() as i64;
+    STEP_COST * (dx + dy) + (DIAG_COST - 2 * STEP_COST) * dx.min(dy)
+}
+
+/// A* from `start` to `target` over the same movement graph as `reachable_tiles`, stopping as soon
+/// as the target is settled. Lodestone, item and ungated interface-slot teleports are usable from the
+/// start tile; door/object/NPC and origin-gated interface transitions only from their origin tile.
+/// Long-range transitions make the octile heuristic inadmissible, so the returned cost is an upper
+/// bound rather than a guarantee.
+fn find_path_to_target(
+    conn: &Connection,
+    start: Tile,
+    target: Tile,
+    overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
+    kinds: &[TeleportKind],
+) -> Result<Option<(i64, Vec<Tile>)>> {
+    let (Some(start_id), Some(target_id)) = (
+        TileId::pack(start.0, start.1, start.2),
+        TileId::pack(target.0, target.1, target.2),
+    ) else {
+        bail!("Start {:?} or target {:?} is outside the supported coordinate range", start, target);
+    };
+
+    let tr = Transitions::load(conn, kinds)?;
+
+    let mut cache = WalkCache::new_with_overrides(overrides);
+    let mut open: BinaryHeap<Reverse<(i64, i64, u64)>> = BinaryHeap::new();
+    let mut best: HashMap<u64, i64> = HashMap::new();
+    let mut came_from: HashMap<u64, u64> = HashMap::new();
+
+    best.insert(start_id.0, 0);
+    open.push(Reverse((octile_distance(start, target), 0, start_id.0)));
+
+    println!("Searching for target {:?} from {:?}", target, start);
+    let mut expanded = 0usize;
+    while let Some(Reverse((_, g, id))) = open.pop() {
+        if best.get(&id).is_some_and(|&b| g > b) {
+            continue;
+        }
+        if id == target_id.0 {
+            let mut path = vec![TileId(id).unpack()];
+            let mut cur = id;
+            while let Some(&prev) = came_from.get(&cur) {
+                path.push(TileId(prev).unpack());
+                cur = prev;
+            }
+            path.reverse();
+            println!("Reached target after expanding {} tiles", expanded);
+            return Ok(Some((g, path)));
+        }
+        expanded += 1;
+        if expanded.is_multiple_of(10000) {
+            println!("Expanded {} tiles so far; open set {}", expanded, open.len());
+        }
+
+        let t = TileId(id).unpack();
+        let mut edges: Vec<(Tile, i64)> = Vec::new();
+        let rec = cache.get_reconciled(conn, t)?;
+        for n in neighbors_from_reconciled(&rec, t) {
+            let cost = if n.0 != t.0 && n.1 != t.1 { DIAG_COST } else { STEP_COST };
+            edges.push((n, cost));
+        }
+        edges.extend(transition_targets(&tr, t, id == start_id.0).into_iter().map(|n| (n, TRANSITION_COST)));
+
+        for (n, cost) in edges {
+            let nid = tile_id(n)?;
+            let ng = g + cost;
+            if best.get(&nid.0).is_some_and(|&b| ng >= b) {
+                continue;
+            }
+            best.insert(nid.0, ng);
+            came_from.insert(nid.0, id);
+            open.push(Reverse((ng + octile_distance(n, target), ng, nid.0)));
+        }
+    }
+
+    println!("Search exhausted after expanding {} tiles", expanded);
+    Ok(None)
+}
+
 fn get_create_table_sql(conn: &Connection, table: &str) -> Result<String> {
     let sql: Option<String> = conn
         .query_row(
@@ -587,14 +630,14 @@ fn get_tiles_row(conn: &Connection, cols: &[String], t: Tile) -> Result<Option<V
     }
 }
 
-fn sanitize_walk_mask_for_reachable(base: &HashMap<String, bool>, tile: Tile, reachable: &HashSet<Tile>) -> i64 {
+fn sanitize_walk_mask_for_reachable(base: &HashMap<String, bool>, tile: Tile, reachable: &HashSet<u64>) -> i64 {
     let (x, y, p) = tile;
     let mut m = base.clone();
     for (k, v) in base.iter() {
         if !*v { continue; }
         if let Some((dx, dy, dp)) = key_delta(k) {
             let n = (x + dx, y + dy, p + dp);
-            if !reachable.contains(&n) {
+            if !is_reachable(reachable, n) {
                 m.insert(k.clone(), false);
             }
         }
@@ -608,18 +651,23 @@ fn create_tiles_and_insert(
     // Use file path so workers can open their own read connections
     src_db_path: &Path,
     dst: &mut Connection,
-    reachable: &HashSet<Tile>,
+    reachable: &HashSet<u64>,
     overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
+    pool: &std::sync::Arc<rayon::ThreadPool>,
+    merge: bool,
 ) -> Result<()> {
     println!("Creating destination tiles table and inserting reachable tiles...");
     let create_sql = get_create_table_sql(src_meta, "tiles")?;
     let cols = get_table_columns(src_meta, "tiles")?;
     let placeholders = (0..cols.len()).map(|_| "?").collect::<Vec<_>>().join(", ");
-    let insert_sql = format!("INSERT INTO tiles ({}) VALUES ({})", cols.join(", "), placeholders);
+    let verb = if merge { "INSERT OR REPLACE" } else { "INSERT" };
+    let insert_sql = format!("{} INTO tiles ({}) VALUES ({})", verb, cols.join(", "), placeholders);
 
     // Prepare destination: create table and start single writer transaction
     let tx = dst.transaction()?;
-    tx.execute(&create_sql, [])?;
+    if !(merge && table_exists(&tx, "tiles")?) {
+        tx.execute(&create_sql, [])?;
+    }
 
     // Channel between producers and single DB writer (this thread)
     let (tx_rows, rx_rows) = mpsc::channel::<Vec<Vec<Value>>>();
@@ -638,9 +686,10 @@ fn create_tiles_and_insert(
         let cols_len = cols.len();
         let src_path = src_db_path.to_path_buf();
         let overrides_arc = overrides.clone();
-        thread::spawn(move || {
+        let pool = pool.clone();
+        thread::spawn(move || pool.install(|| {
             // Process tiles in parallel and stream to writer in batches
-            let mut tiles: Vec<Tile> = reachable_arc.iter().copied().collect();
+            let mut tiles: Vec<Tile> = reachable_arc.iter().map(|&id| TileId(id).unpack()).collect();
             tiles.shrink_to_fit();
             const BATCH: usize = 10_000;
 
@@ -697,7 +746,7 @@ fn create_tiles_and_insert(
                     if !out.is_empty() { let _ = sender.send(out); }
                 });
             // Dropping tx_rows closes channel
-        })
+        }))
     };
 
     drop(tx_rows);
@@ -736,7 +785,111 @@ fn create_tiles_and_insert(
     Ok(())
 }
 
-fn copy_tables(src: &Connection, dst: &mut Connection, skip: &HashSet<String>) -> Result<()> {
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type='table' AND name=?1", [table], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+fn surviving_tiles(dst: &Connection) -> Result<HashSet<u64>> {
+    let mut stmt = dst.prepare("SELECT x, y, plane FROM tiles")?;
+    let mut rows = stmt.query([])?;
+    let mut out = HashSet::new();
+    while let Some(r) = rows.next()? {
+        if let Some(id) = TileId::pack(r.get(0)?, r.get(1)?, r.get(2)?) {
+            out.insert(id.0);
+        }
+    }
+    Ok(out)
+}
+
+/// Tile endpoints of each teleport table, as the reachability search reads them: three columns are
+/// an exact tile (x, y, plane), five are a box (min_x, max_x, min_y, max_y, plane) reduced to its
+/// center tile.
+const TELEPORT_ENDPOINTS: &[(&str, &[&[&str]])] = &[
+    (
+        "teleports_door_nodes",
+        &[
+            &["tile_inside_x", "tile_inside_y", "tile_inside_plane"],
+            &["tile_outside_x", "tile_outside_y", "tile_outside_plane"],
+        ],
+    ),
+    ("teleports_lodestone_nodes", &[&["dest_x", "dest_y", "dest_plane"]]),
+    ("teleports_fairy_rings_nodes", &[&["x", "y", "plane"]]),
+    (
+        "teleports_object_nodes",
+        &[
+            &["orig_min_x", "orig_max_x", "orig_min_y", "orig_max_y", "orig_plane"],
+            &["dest_min_x", "dest_max_x", "dest_min_y", "dest_max_y", "dest_plane"],
+        ],
+    ),
+    (
+        "teleports_npc_nodes",
+        &[
+            &["orig_min_x", "orig_max_x", "orig_min_y", "orig_max_y", "orig_plane"],
+            &["dest_min_x", "dest_max_x", "dest_min_y", "dest_max_y", "dest_plane"],
+        ],
+    ),
+    ("teleports_item_nodes", &[&["dest_min_x", "dest_max_x", "dest_min_y", "dest_max_y", "dest_plane"]]),
+    (
+        "teleports_ifslot_nodes",
+        &[
+            &["orig_min_x", "orig_max_x", "orig_min_y", "orig_max_y", "orig_plane"],
+            &["dest_min_x", "dest_max_x", "dest_min_y", "dest_max_y", "dest_plane"],
+        ],
+    ),
+];
+
+/// Endpoint column groups of `table` that exist in `src_cols`.
+fn teleport_endpoints(table: &str, src_cols: &[String]) -> Vec<&'static [&'static str]> {
+    TELEPORT_ENDPOINTS
+        .iter()
+        .find(|(t, _)| *t == table)
+        .map(|(_, eps)| {
+            eps.iter()
+                .copied()
+                .filter(|ep| ep.iter().all(|c| src_cols.iter().any(|s| s == c)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// False when any fully populated endpoint of a row lies outside `reachable` (the output's tiles). `vals` holds the
+/// endpoint columns in `endpoints` order; endpoints with a NULL column are not checked, matching the
+/// search, which ignores them.
+fn endpoints_in_scope(endpoints: &[&[&str]], vals: &[Value], reachable: &HashSet<u64>) -> bool {
+    let mut offset = 0;
+    for ep in endpoints {
+        let cols = &vals[offset..offset + ep.len()];
+        offset += ep.len();
+        let ints: Option<Vec<i32>> = cols
+            .iter()
+            .map(|v| match v {
+                Value::Integer(n) => Some(*n as i32),
+                _ => None,
+            })
+            .collect();
+        let Some(ints) = ints else { continue };
+        let tile = match ints[..] {
+            [x, y, plane] => (x, y, plane),
+            [min_x, max_x, min_y, max_y, plane] => center_tile(min_x, max_x, min_y, max_y, plane),
+            _ => continue,
+        };
+        if !is_reachable(reachable, tile) {
+            return false;
+        }
+    }
+    true
+}
+
+fn copy_tables(
+    src: &Connection,
+    dst: &mut Connection,
+    skip: &HashSet<String>,
+    merge: bool,
+    scope: Option<&HashSet<u64>>,
+) -> Result<()> {
     let mut stmt = src.prepare("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'")?;
     let table_names: Vec<String> = stmt
         .query_map([], |row| row.get::<_, String>(0))?
@@ -755,18 +908,50 @@ fn copy_tables(src: &Connection, dst: &mut Connection, skip: &HashSet<String>) -
             Ok(Some(s)) => s,
             _ => continue,
         };
-        tx.execute(&create_sql, [])?;
-        let cols = get_table_columns(src, &t)?;
+        let src_cols = get_table_columns(src, &t)?;
+        let dst_exists = table_exists(&tx, &t)?;
+        // A pre-existing destination table may lag behind the source schema: copy only the shared
+        // columns instead of failing on the first legacy one.
+        let cols = if dst_exists {
+            let dst_cols = get_table_columns(&tx, &t)?;
+            let (shared, dropped): (Vec<String>, Vec<String>) =
+                src_cols.into_iter().partition(|c| dst_cols.contains(c));
+            if !dropped.is_empty() {
+                println!("  Skipping columns of `{}` missing from destination: {}", t, dropped.join(", "));
+            }
+            shared
+        } else {
+            tx.execute(&create_sql, [])?;
+            src_cols
+        };
         if cols.is_empty() { continue; }
-        let select_sql = format!("SELECT {} FROM {}", cols.join(", "), t);
+        let endpoints = match scope {
+            Some(_) => teleport_endpoints(&t, &get_table_columns(src, &t)?),
+            None => Vec::new(),
+        };
+        let endpoint_cols: Vec<&str> = endpoints.iter().flat_map(|ep| ep.iter().copied()).collect();
+        let select_sql = format!(
+            "SELECT {} FROM {}",
+            cols.iter().map(String::as_str).chain(endpoint_cols.iter().copied()).collect::<Vec<_>>().join(", "),
+            t
+        );
         let placeholders = (0..cols.len()).map(|_| "?").collect::<Vec<_>>().join(", ");
-        let insert_sql = format!("INSERT INTO {} ({}) VALUES ({})", t, cols.join(", "), placeholders);
+        let verb = if merge { "INSERT OR REPLACE" } else { "INSERT" };
+        let insert_sql = format!("{} INTO {} ({}) VALUES ({})", verb, t, cols.join(", "), placeholders);
         let mut ins = tx.prepare(&insert_sql)?;
         let mut sel = src.prepare(&select_sql)?;
         let mut rows = sel.query([])?;
         let mut copied = 0usize;
+        let mut dropped = 0usize;
         while let Some(r) = rows.next()? {
-            let vals = read_row_values(r, cols.len())?;
+            let mut vals = read_row_values(r, cols.len() + endpoint_cols.len())?;
+            let endpoint_vals = vals.split_off(cols.len());
+            if let Some(reachable) = scope {
+                if !endpoints_in_scope(&endpoints, &endpoint_vals, reachable) {
+                    dropped += 1;
+                    continue;
+                }
+            }
             ins.execute(params_from_iter(vals.into_iter()))?;
             copied += 1;
             if copied % 5000 == 0 {
@@ -774,6 +959,9 @@ fn copy_tables(src: &Connection, dst: &mut Connection, skip: &HashSet<String>) -
             }
         }
         println!("  Finished copying {} rows into `{}`", copied, t);
+        if dropped > 0 {
+            println!("  Dropped {} rows of `{}` with an endpoint outside the output tiles", dropped, t);
+        }
         let mut idx_stmt = src.prepare(
             "SELECT sql FROM sqlite_master WHERE type='index' AND tbl_name=?1 AND sql IS NOT NULL",
         )?;
@@ -809,34 +997,517 @@ fn copy_views(src: &Connection, dst: &mut Connection) -> Result<()> {
     Ok(())
 }
 
-pub fn cmd_tile_cleaner(src_db: &Path, out_db: &Path, start_x: i32, start_y: i32, start_plane: i32) -> Result<()> {
-    println!("Starting tile cleaner from start tile ({}, {}, {})", start_x, start_y, start_plane);
+/// Stores the teleport kinds used for this run in the output's `meta` table (copied from the
+/// source). Sources from before schema version 2 reject the key; that only earns a warning.
+fn record_teleport_kinds(dst: &Connection, kinds: &[TeleportKind]) -> Result<()> {
+    if !table_exists(dst, "meta")? {
+        return Ok(());
+    }
+    let value = TeleportKind::ALL
+        .iter()
+        .filter(|k| kinds.contains(k))
+        .map(|k| k.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    if crate::db::write_meta(dst, "teleport_kinds", &value)? {
+        println!("Recorded teleport kinds '{}' in meta", value);
+    } else {
+        println!("Warning: source meta table predates teleport_kinds; not recording '{}'", value);
+    }
+    Ok(())
+}
+
+/// Start tile used when neither the CLI nor `meta.reachability_start` gives one.
+const DEFAULT_START: Tile = (3200, 3200, 0);
+
+/// Picks the BFS start: CLI coordinates win, missing ones come from the source's
+/// `meta.reachability_start`, then `DEFAULT_START`. Also returns whether the start differs from
+/// the stored one because of CLI coordinates, i.e. whether `store_start` should record it.
+fn resolve_start(src: &Connection, x: Option<i32>, y: Option<i32>, plane: Option<i32>) -> Result<(Tile, bool)> {
+    let stored = match crate::db::read_meta(src, "reachability_start")? {
+        Some(v) => Some(
+            crate::util::parse_tile_arg(&v)
+                .map_err(|e| anyhow!("Invalid meta.reachability_start: {}", e))?,
+        ),
+        None => None,
+    };
+    let base = stored.unwrap_or(DEFAULT_START);
+    let start = (x.unwrap_or(base.0), y.unwrap_or(base.1), plane.unwrap_or(base.2));
+    let from_cli = x.is_some() || y.is_some() || plane.is_some();
+    if !from_cli && stored.is_some() {
+        println!("Using start tile {:?} from meta.reachability_start", start);
+    }
+    Ok((start, from_cli && stored != Some(start)))
+}
+
+/// Saves a CLI-given start in the source's `meta.reachability_start` so later runs can omit it.
+/// Only called once a run has succeeded, so a mistyped start is never remembered.
+fn store_start(src: &Connection, start: Tile) -> Result<()> {
+    let value = format!("{},{},{}", start.0, start.1, start.2);
+    if crate::db::write_meta(src, "reachability_start", &value)? {
+        println!("Stored start tile {} in meta.reachability_start", value);
+    } else {
+        println!("Warning: source has no meta table or it predates reachability_start; start tile not stored");
+    }
+    Ok(())
+}
+
+/// What to do when the output DB already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DestDbExists {
+    /// Stop with an error and leave the file alone
+    Fail,
+    /// Delete it and write a fresh DB
+    Overwrite,
+    /// Add this run's reachable tiles and tables to it, replacing rows with the same key
+    Merge,
+}
+
+pub struct CleanerOptions<'a> {
+    /// Start coordinates given on the CLI; unset ones fall back to `meta.reachability_start`.
+    pub start_x: Option<i32>,
+    pub start_y: Option<i32>,
+    pub start_plane: Option<i32>,
+    pub target: Option<Tile>,
+    pub threads: Option<usize>,
+    pub dest_db_exists: DestDbExists,
+    /// Write the reachable set to this file (see `reachable_bitmap`) instead of an output DB.
+    pub emit_bitmap: Option<&'a Path>,
+    /// Drop teleport rows whose origin or destination tile did not make it into the output DB.
+    pub keep_teleports_in_scope: bool,
+    /// Teleport kinds the search may use; recorded in the output's `meta.teleport_kinds`.
+    pub teleport_kinds: &'a [TeleportKind],
+    /// Keep only tiles within this many steps of the start (see `reachable_tiles_within`).
+    pub max_depth: Option<u32>,
+    /// Depth charged for a teleport/door hop under `max_depth`.
+    pub teleport_depth_cost: u32,
+    /// Run even when the source `tiles` table is empty (producing an empty world).
+    pub allow_empty: bool,
+}
+
+/// Opens the source DB and checks its schema; also returns whether it has any tiles.
+fn open_source(src_db: &Path) -> Result<(Connection, bool)> {
     let src = Connection::open(src_db).with_context(|| format!("Open DB {}", src_db.display()))?;
     println!("Opened source database {}", src_db.display());
+    crate::db::ensure_supported_schema(&src, src_db)?;
     src.execute_batch("PRAGMA foreign_keys=ON;")?;
-    let start: Tile = (start_x, start_y, start_plane);
+    let has_tiles = src.query_row("SELECT EXISTS (SELECT 1 FROM tiles)", [], |r| r.get::<_, bool>(0))?;
+    Ok((src, has_tiles))
+}
+
+pub fn cmd_tile_cleaner(src_db: &Path, out_db: &Path, opts: &CleanerOptions) -> Result<()> {
+    let CleanerOptions {
+        start_x,
+        start_y,
+        start_plane,
+        target,
+        threads,
+        dest_db_exists,
+        emit_bitmap,
+        keep_teleports_in_scope,
+        teleport_kinds,
+        max_depth,
+        teleport_depth_cost,
+        allow_empty,
+    } = *opts;
+    let (src, has_tiles) = with_lock_hint(open_source(src_db), src_db)?;
+    if !has_tiles {
+        if !allow_empty {
+            bail!(
+                "Source {} has no tiles; run load-tiles first (or pass --allow-empty to write an empty world)",
+                src_db.display()
+            );
+        }
+        println!("Warning: source {} has no tiles; continuing because of --allow-empty", src_db.display());
+    }
+    let (start, store) = with_lock_hint(resolve_start(&src, start_x, start_y, start_plane), src_db)?;
+    println!("Starting tile cleaner from start tile ({}, {}, {})", start.0, start.1, start.2);
+
+    if let Some(target) = target {
+        // Reachability query only: no output DB is written.
+        let overrides = with_lock_hint(build_fairy_ring_overrides(&src), src_db)?;
+        match with_lock_hint(find_path_to_target(&src, start, target, overrides, teleport_kinds), src_db)? {
+            Some((cost, path)) => println!(
+                "Target {:?} is reachable: cost {} (cardinal step = {}), {} tiles on path",
+                target,
+                cost,
+                STEP_COST,
+                path.len()
+            ),
+            None => println!("Target {:?} is not reachable from {:?}", target, start),
+        }
+        return Ok(());
+    }
+    // Checked before the BFS so a forgotten output file fails fast instead of after minutes of work.
+    if emit_bitmap.is_none() && out_db.exists() && dest_db_exists == DestDbExists::Fail {
+        bail!(
+            "Output database {} already exists; pass --dest-db-exists overwrite or merge",
+            out_db.display()
+        );
+    }
     println!("Computing reachable tiles...");
-    let overrides = build_fairy_ring_overrides(&src)?;
-    let reachable = reachable_tiles(&src, start, overrides.clone())?;
+    let overrides = with_lock_hint(build_fairy_ring_overrides(&src), src_db)?;
+    let reachable = match max_depth {
+        Some(max_depth) => with_lock_hint(
+            reachable_tiles_within(&src, start, overrides.clone(), teleport_kinds, max_depth, teleport_depth_cost),
+            src_db,
+        )?,
+        None => with_lock_hint(reachable_tiles(&src, start, overrides.clone(), teleport_kinds), src_db)?,
+    };
     println!("Identified {} reachable tiles", reachable.len());
 
+    if let Some(bitmap_path) = emit_bitmap {
+        let regions = crate::reachable_bitmap::write_reachable_bitmap(bitmap_path, &reachable)?;
+        println!("Wrote reachable bitmap ({} regions) to {}", regions, bitmap_path.display());
+        if store {
+            with_lock_hint(store_start(&src, start), src_db)?;
+        }
+        return Ok(());
+    }
+
+    let merge = out_db.exists() && dest_db_exists == DestDbExists::Merge;
     if out_db.exists() {
-        println!("Removing existing output database {}", out_db.display());
-        let _ = fs::remove_file(out_db);
+        match dest_db_exists {
+            DestDbExists::Overwrite => {
+                println!("Removing existing output database {}", out_db.display());
+                fs::remove_file(out_db)
+                    .with_context(|| format!("Remove existing output DB {}", out_db.display()))?;
+            }
+            DestDbExists::Merge => println!("Merging into existing output database {}", out_db.display()),
+            DestDbExists::Fail => unreachable!("checked before the BFS"),
+        }
     }
     let mut dst = Connection::open(out_db).with_context(|| format!("Create DB {}", out_db.display()))?;
     println!("Opened destination database {}", out_db.display());
+    if merge {
+        // Merged rows must fit the existing output's schema, not just the source's.
+        with_lock_hint(crate::db::ensure_supported_schema(&dst, out_db), out_db)?;
+    }
     // Match Python behavior: avoid FK errors while creating/inserting tiles before copying 'chunks'
-    dst.execute_batch("PRAGMA foreign_keys=OFF;")?;
+    with_lock_hint(dst.execute_batch("PRAGMA foreign_keys=OFF;").map_err(Into::into), out_db)?;
     println!("Disabled foreign key checks on destination");
 
-    create_tiles_and_insert(&src, src_db, &mut dst, &reachable, overrides)?;
+    let both = [src_db, out_db];
+    let pool = crate::util::build_thread_pool(threads)?;
+    with_lock_hints(create_tiles_and_insert(&src, src_db, &mut dst, &reachable, overrides, &pool, merge), &both)?;
 
     let mut skip = HashSet::new();
     skip.insert("tiles".to_string());
-    copy_tables(&src, &mut dst, &skip)?;
-    copy_views(&src, &mut dst)?;
+    // Scope is what survived into the output: seeded teleport endpoints enter the BFS visited set
+    // even when the source has no tile row for them.
+    let scope = if keep_teleports_in_scope { Some(with_lock_hint(surviving_tiles(&dst), out_db)?) } else { None };
+    with_lock_hints(copy_tables(&src, &mut dst, &skip, merge, scope.as_ref()), &both)?;
+    with_lock_hints(copy_views(&src, &mut dst), &both)?;
+    with_lock_hint(record_teleport_kinds(&dst, teleport_kinds), out_db)?;
+    if store {
+        with_lock_hint(store_start(&src, start), src_db)?;
+    }
 
     println!("Tile cleaning complete; output written to {}", out_db.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch, tiles_db, OPEN};
+
+    fn no_overrides() -> std::sync::Arc<HashMap<Tile, WalkMaskOverride>> {
+        std::sync::Arc::new(HashMap::new())
+    }
+
+    fn opts() -> CleanerOptions<'static> {
+        CleanerOptions {
+            start_x: Some(0),
+            start_y: Some(0),
+            start_plane: Some(0),
+            target: None,
+            threads: Some(2),
+            dest_db_exists: DestDbExists::Fail,
+            emit_bitmap: None,
+            keep_teleports_in_scope: false,
+            teleport_kinds: TeleportKind::ALL,
+            max_depth: None,
+            teleport_depth_cost: 1,
+            allow_empty: false,
+        }
+    }
+
+    #[test]
+    fn locked_source_is_named_in_the_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        let out = dir.path().join("out.db");
+        let lock = tiles_db(&src, &[(0, 0, 0, Some(OPEN))]);
+        lock.execute_batch("BEGIN EXCLUSIVE;").unwrap();
+
+        let err = cmd_tile_cleaner(&src, &out, &opts()).unwrap_err().to_string();
+        assert!(err.contains(&format!("SQLite database {} is locked", src.display())), "{err}");
+        assert!(!err.contains(&out.display().to_string()), "{err}");
+    }
+
+    fn stored_start(src: &Path) -> Option<String> {
+        crate::db::read_meta(&Connection::open(src).unwrap(), "reachability_start").unwrap()
+    }
+
+    fn out_tiles(out: &Path) -> i64 {
+        Connection::open(out).unwrap().query_row("SELECT COUNT(*) FROM tiles", [], |r| r.get(0)).unwrap()
+    }
+
+    /// Two walkable islands: x 0..=2 and x 10..=11 on row y=0.
+    fn islands(src: &Path) -> Connection {
+        tiles_db(
+            src,
+            &[(0, 0, 0, Some(OPEN)), (1, 0, 0, Some(OPEN)), (2, 0, 0, Some(OPEN)), (10, 0, 0, Some(OPEN)), (11, 0, 0, Some(OPEN))],
+        )
+    }
+
+    #[test]
+    fn cli_start_is_stored_after_success_and_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        drop(islands(&src));
+        let start_at = |x| CleanerOptions { start_x: Some(x), start_y: Some(0), start_plane: Some(0), ..opts() };
+        let no_start = CleanerOptions { start_x: None, start_y: None, start_plane: None, ..opts() };
+
+        // Failed runs (output exists) and --target queries leave meta alone.
+        let out = dir.path().join("out1.db");
+        fs::write(&out, b"").unwrap();
+        assert!(cmd_tile_cleaner(&src, &out, &start_at(10)).is_err());
+        cmd_tile_cleaner(&src, &dir.path().join("q.db"), &CleanerOptions { target: Some((11, 0, 0)), ..start_at(10) })
+            .unwrap();
+        assert_eq!(stored_start(&src), None);
+
+        let out = dir.path().join("out2.db");
+        cmd_tile_cleaner(&src, &out, &start_at(10)).unwrap();
+        assert_eq!(stored_start(&src).as_deref(), Some("10,0,0"));
+        assert_eq!(out_tiles(&out), 2);
+
+        let out = dir.path().join("out3.db");
+        cmd_tile_cleaner(&src, &out, &no_start).unwrap();
+        assert_eq!(out_tiles(&out), 2);
+    }
+
+    #[test]
+    fn dest_db_exists_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        drop(islands(&src));
+        let out = dir.path().join("out.db");
+        cmd_tile_cleaner(&src, &out, &opts()).unwrap();
+        assert_eq!(out_tiles(&out), 3);
+
+        let err = cmd_tile_cleaner(&src, &out, &CleanerOptions { start_x: Some(10), ..opts() }).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+
+        let merge = CleanerOptions { start_x: Some(10), dest_db_exists: DestDbExists::Merge, ..opts() };
+        cmd_tile_cleaner(&src, &out, &merge).unwrap();
+        assert_eq!(out_tiles(&out), 5);
+
+        let overwrite = CleanerOptions { start_x: Some(10), dest_db_exists: DestDbExists::Overwrite, ..opts() };
+        cmd_tile_cleaner(&src, &out, &overwrite).unwrap();
+        assert_eq!(out_tiles(&out), 2);
+
+        // Merging into an output stamped by a newer build is refused before anything is written.
+        Connection::open(&out)
+            .unwrap()
+            .execute("UPDATE meta SET value = '999' WHERE key = 'schema_version'", [])
+            .unwrap();
+        let err = cmd_tile_cleaner(&src, &out, &CleanerOptions { dest_db_exists: DestDbExists::Merge, ..opts() })
+            .unwrap_err();
+        assert!(err.to_string().contains("schema_version 999"), "{err}");
+        assert_eq!(out_tiles(&out), 2);
+    }
+
+    #[test]
+    fn emitted_bitmap_matches_the_bfs_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        let conn = islands(&src);
+        let bfs = reachable_tiles(&conn, (0, 0, 0), no_overrides(), TeleportKind::ALL).unwrap();
+
+        let bitmap = dir.path().join("reach.bin");
+        let out = dir.path().join("out.db");
+        cmd_tile_cleaner(&src, &out, &CleanerOptions { emit_bitmap: Some(&bitmap), ..opts() }).unwrap();
+        assert!(!out.exists());
+        let loaded = crate::reachable_bitmap::load_reachable_bitmap(&bitmap).unwrap();
+        assert_eq!(loaded.into_iter().map(|id| id.0).collect::<HashSet<_>>(), bfs);
+        assert_eq!(bfs.len(), 3);
+    }
+
+    fn add_door(conn: &Connection, id: i64, inside: Tile, outside: Tile) {
+        conn.execute(
+            "INSERT INTO teleports_door_nodes (id, tile_inside_x, tile_inside_y, tile_inside_plane, \
+             tile_outside_x, tile_outside_y, tile_outside_plane) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, inside.0, inside.1, inside.2, outside.0, outside.1, outside.2],
+        )
+        .unwrap();
+    }
+
+    /// x coordinates (all on y=0, plane 0) of a packed tile set, sorted.
+    fn xs(set: &HashSet<u64>) -> Vec<i32> {
+        let mut xs: Vec<i32> = set.iter().map(|&id| TileId(id).unpack().0).collect();
+        xs.sort();
+        xs
+    }
+
+    #[test]
+    fn max_depth_bounds_walking_and_charges_teleports() {
+        let (_dir, path) = scratch("tiles.db");
+        let mut tiles: Vec<_> = (0..10).map(|x| (x, 0, 0, Some(OPEN))).collect();
+        tiles.extend([(20, 0, 0, Some(OPEN)), (21, 0, 0, Some(OPEN))]);
+        let conn = tiles_db(&path, &tiles);
+        add_door(&conn, 1, (20, 0, 0), (1, 0, 0));
+
+        let within = |depth, teleport_cost| {
+            xs(&reachable_tiles_within(&conn, (0, 0, 0), no_overrides(), TeleportKind::ALL, depth, teleport_cost).unwrap())
+        };
+        // Step to x=1 (depth 1), then the door hop to x=20 (depth 2).
+        assert_eq!(within(2, 1), vec![0, 1, 2, 20]);
+        assert_eq!(within(3, 1), vec![0, 1, 2, 3, 20, 21]);
+        // A hop costing 2 puts x=20 at depth 3.
+        assert_eq!(within(2, 2), vec![0, 1, 2]);
+        assert_eq!(within(3, 2), vec![0, 1, 2, 3, 20]);
+        assert_eq!(within(0, 1), vec![0]);
+    }
+
+    #[test]
+    fn keep_teleports_in_scope_drops_rows_with_missing_endpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        let conn = islands(&src);
+        add_door(&conn, 1, (0, 0, 0), (1, 0, 0));
+        // No tile row at (50, 50): the endpoint can never be part of the output.
+        add_door(&conn, 2, (2, 0, 0), (50, 50, 0));
+        drop(conn);
+        let door_ids = |out: &Path| -> Vec<i64> {
+            let conn = Connection::open(out).unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM teleports_door_nodes ORDER BY id").unwrap();
+            let ids = stmt.query_map([], |r| r.get(0)).unwrap().collect::<rusqlite::Result<Vec<i64>>>().unwrap();
+            ids
+        };
+
+        let out = dir.path().join("all.db");
+        cmd_tile_cleaner(&src, &out, &opts()).unwrap();
+        assert_eq!(door_ids(&out), vec![1, 2]);
+
+        let out = dir.path().join("scoped.db");
+        cmd_tile_cleaner(&src, &out, &CleanerOptions { keep_teleports_in_scope: true, ..opts() }).unwrap();
+        assert_eq!(door_ids(&out), vec![1]);
+    }
+
+    #[test]
+    fn excluded_teleport_kinds_are_not_used_and_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        let conn = islands(&src);
+        conn.execute("INSERT INTO teleports_lodestone_nodes (id, dest_x, dest_y, dest_plane) VALUES (1, 10, 0, 0)", [])
+            .unwrap();
+        drop(conn);
+        let kinds = |out: &Path| crate::db::read_meta(&Connection::open(out).unwrap(), "teleport_kinds").unwrap();
+
+        let out = dir.path().join("all.db");
+        cmd_tile_cleaner(&src, &out, &opts()).unwrap();
+        assert_eq!(out_tiles(&out), 5);
+        assert_eq!(kinds(&out).as_deref(), Some("door,lodestone,object,npc,item,ifslot"));
+
+        let out = dir.path().join("no_lodestones.db");
+        let no_lodestones = [TeleportKind::Door, TeleportKind::Npc];
+        cmd_tile_cleaner(&src, &out, &CleanerOptions { teleport_kinds: &no_lodestones, ..opts() }).unwrap();
+        let conn = Connection::open(&out).unwrap();
+        let lodestone_island: i64 =
+            conn.query_row("SELECT COUNT(*) FROM tiles WHERE x >= 10", [], |r| r.get(0)).unwrap();
+        assert_eq!(lodestone_island, 0);
+        assert_eq!(out_tiles(&out), 3);
+        assert_eq!(kinds(&out).as_deref(), Some("door,npc"));
+    }
+
+    #[test]
+    fn empty_source_fails_unless_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.db");
+        drop(tiles_db(&src, &[]));
+        let out = dir.path().join("out.db");
+
+        let err = cmd_tile_cleaner(&src, &out, &opts()).unwrap_err();
+        assert!(err.to_string().contains("has no tiles"), "{err}");
+        assert!(!out.exists());
+
+        cmd_tile_cleaner(&src, &out, &CleanerOptions { allow_empty: true, ..opts() }).unwrap();
+        assert_eq!(out_tiles(&out), 0);
+    }
+
+    #[test]
+    fn origin_gated_ifslots_need_their_origin_reached() {
+        let (_dir, path) = scratch("tiles.db");
+        let conn = islands(&path);
+        let reached = |conn: &Connection| xs(&reachable_tiles(conn, (0, 0, 0), no_overrides(), TeleportKind::ALL).unwrap());
+        let set_ifslot = |origin: Option<Tile>| {
+            conn.execute("DELETE FROM teleports_ifslot_nodes", []).unwrap();
+            conn.execute(
+                "INSERT INTO teleports_ifslot_nodes (id, dest_min_x, dest_max_x, dest_min_y, dest_max_y, dest_plane, \
+                 orig_min_x, orig_max_x, orig_min_y, orig_max_y, orig_plane) VALUES (1, 10, 10, 0, 0, 0, ?1, ?1, ?2, ?2, ?3)",
+                params![origin.map(|t| t.0), origin.map(|t| t.1), origin.map(|t| t.2)],
+            )
+            .unwrap();
+        };
+
+        set_ifslot(Some((2, 0, 0)));
+        assert_eq!(reached(&conn), vec![0, 1, 2, 10, 11]);
+        set_ifslot(Some((50, 50, 0)));
+        assert_eq!(reached(&conn), vec![0, 1, 2]);
+        set_ifslot(None);
+        assert_eq!(reached(&conn), vec![0, 1, 2, 10, 11]);
+    }
+
+    #[test]
+    fn copy_tables_copies_shared_columns_into_an_older_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = tiles_db(&dir.path().join("src.db"), &[]);
+        src.execute("INSERT INTO teleports_door_nodes (id, cost, requirements) VALUES (7, 3, 'r')", []).unwrap();
+        let mut dst = Connection::open(dir.path().join("dst.db")).unwrap();
+        dst.execute_batch("CREATE TABLE teleports_door_nodes (id INTEGER PRIMARY KEY, cost INTEGER, legacy TEXT);")
+            .unwrap();
+
+        let skip: HashSet<String> = ["tiles".to_string()].into();
+        copy_tables(&src, &mut dst, &skip, true, None).unwrap();
+        let row: (i64, i64, Option<String>) = dst
+            .query_row("SELECT id, cost, legacy FROM teleports_door_nodes", [], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap();
+        assert_eq!(row, (7, 3, None));
+        // Tables the destination lacked are created with the source schema.
+        assert!(table_exists(&dst, "teleports_lodestone_nodes").unwrap());
+    }
+
+    #[test]
+    fn a_star_finds_walkable_and_teleport_paths() {
+        let (_dir, path) = scratch("tiles.db");
+        let conn = islands(&path);
+        let find = |target| find_path_to_target(&conn, (0, 0, 0), target, no_overrides(), TeleportKind::ALL).unwrap();
+
+        let (cost, steps) = find((2, 0, 0)).unwrap();
+        assert_eq!((cost, steps), (2 * STEP_COST, vec![(0, 0, 0), (1, 0, 0), (2, 0, 0)]));
+        assert!(find((11, 0, 0)).is_none());
+        assert!(find((99, 99, 0)).is_none());
+
+        add_door(&conn, 1, (10, 0, 0), (2, 0, 0));
+        let (cost, steps) = find((11, 0, 0)).unwrap();
+        assert_eq!(cost, 2 * STEP_COST + TRANSITION_COST + STEP_COST);
+        assert_eq!(steps.last(), Some(&(11, 0, 0)));
+    }
+
+    #[test]
+    fn unpackable_tiles_fail_the_search() {
+        let (_dir, path) = scratch("tiles.db");
+        let conn = tiles_db(&path, &[(0, 0, 0, Some(OPEN)), (1, 0, 0, Some(OPEN))]);
+
+        let err = reachable_tiles(&conn, (-1, 0, 0), no_overrides(), TeleportKind::ALL).unwrap_err();
+        assert!(err.to_string().contains("(-1, 0, 0)"), "{err}");
+
+        // A teleport landing outside the packable range is reported rather than dropped.
+        conn.execute("INSERT INTO teleports_lodestone_nodes (id, dest_x, dest_y, dest_plane) VALUES (1, 5, 5, 300)", [])
+            .unwrap();
+        let err = reachable_tiles(&conn, (0, 0, 0), no_overrides(), TeleportKind::ALL).unwrap_err();
+        assert!(err.to_string().contains("(5, 5, 300)"), "{err}");
+    }
+}
@@ -1,7 +1,94 @@
-use anyhow::{bail, Result};
-use rusqlite::{Connection, OptionalExtension};
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, ErrorCode, OptionalExtension};
+use std::path::Path;
+
+/// Durability/throughput tradeoff for bulk writes; `description` documents each mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqliteMode {
+    Fast,
+    Safe,
+    Memory,
+}
+
+impl SqliteMode {
+    /// Lowercase name, as given to `--sqlite-mode`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SqliteMode::Fast => "fast",
+            SqliteMode::Safe => "safe",
+            SqliteMode::Memory => "memory",
+        }
+    }
+
+    /// What the mode trades away; also the `--sqlite-mode` help text.
+    pub fn description(self) -> &'static str {
+        match self {
+            SqliteMode::Fast => {
+                "WAL journal with synchronous=NORMAL: fast, survives app crashes but may lose the last commit on power loss"
+            }
+            SqliteMode::Safe => "WAL journal with synchronous=FULL: slowest, durable across power loss",
+            SqliteMode::Memory => {
+                "In-memory journal with synchronous=OFF: fastest, a crash mid-load can corrupt the DB (ramdisk/scratch use)"
+            }
+        }
+    }
+
+    pub fn pragmas(self) -> &'static str {
+        match self {
+            SqliteMode::Fast => "PRAGMA journal_mode=WAL;\nPRAGMA synchronous=NORMAL;\nPRAGMA temp_store=MEMORY;",
+            SqliteMode::Safe => "PRAGMA journal_mode=WAL;\nPRAGMA synchronous=FULL;\nPRAGMA temp_store=MEMORY;",
+            SqliteMode::Memory => "PRAGMA journal_mode=MEMORY;\nPRAGMA synchronous=OFF;\nPRAGMA temp_store=MEMORY;",
+        }
+    }
+
+    pub fn apply(self, conn: &Connection) -> Result<()> {
+        conn.execute_batch(self.pragmas())?;
+        Ok(())
+    }
+}
+
+/// Stamped into `meta.schema_version` by `create_tables`; bump whenever the schema changes.
+pub const SCHEMA_VERSION: i64 = 4;
+
+fn is_lock_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<rusqlite::Error>()
+            .and_then(|e| e.sqlite_error_code())
+            .is_some_and(|code| matches!(code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked))
+    })
+}
+
+/// Adds the DB path and the likely cause to SQLITE_BUSY/SQLITE_LOCKED failures.
+/// rusqlite already waits up to 5s on a busy DB, so by the time this fires the lock is long-held.
+pub fn with_lock_hint<T>(res: Result<T>, db_path: &Path) -> Result<T> {
+    with_lock_hints(res, &[db_path])
+}
 
-pub fn create_tables(conn: &mut Connection) -> Result<()> {
+/// `with_lock_hint` for a step that reads or writes several DBs (e.g. copying from a source into
+/// an output DB); a busy error does not say which file was locked, so every one is named.
+pub fn with_lock_hints<T>(res: Result<T>, db_paths: &[&Path]) -> Result<T> {
+    res.map_err(|e| {
+        if is_lock_error(&e) {
+            let names: Vec<String> = db_paths.iter().map(|p| p.display().to_string()).collect();
+            let subject = match names.as_slice() {
+                [one] => format!("SQLite database {} is", one),
+                _ => format!("One of the SQLite databases {} is", names.join(", ")),
+            };
+            e.context(format!(
+                "{} locked; another process (a running load-tiles/import-xlsx/tile-cleaner, \
+                 or a DB browser with an open write transaction) is probably holding it",
+                subject
+            ))
+        } else {
+            e
+        }
+    })
+}
+
+/// Creates the full schema and stamps `meta.schema_version`. Safe to run inside a transaction, in
+/// which case the `foreign_keys` pragma is left to the caller.
+pub fn create_tables(conn: &Connection) -> Result<()> {
     conn.execute_batch("PRAGMA foreign_keys = ON;")?;
 
     if schema_has_user_tables(conn)? {
@@ -83,14 +170,15 @@ pub fn create_tables(conn: &mut Connection) -> Result<()> {
 --    PRIMARY KEY (cluster_id, x, y, plane)
 --);
 
---CREATE TABLE meta (
---  key   TEXT PRIMARY KEY
---        CHECK (key IN (
---          'schema_version','tileset_version','map_build_at',
---          'generator_commit', 'coordinate_origin'
---        )),
---  value TEXT NOT NULL
---);
+CREATE TABLE meta (
+  key   TEXT PRIMARY KEY
+        CHECK (key IN (
+          'schema_version','tileset_version','map_build_at',
+          'generator_commit', 'coordinate_origin', 'teleport_kinds',
+          'reachability_start'
+        )),
+  value TEXT NOT NULL
+);
 
 
 CREATE TABLE teleports_door_nodes (
@@ -128,6 +216,11 @@ CREATE TABLE teleports_ifslot_nodes (
     dest_min_y INTEGER,
     dest_max_y INTEGER,
     dest_plane INTEGER,
+    orig_min_x INTEGER,
+    orig_max_x INTEGER,
+    orig_min_y INTEGER,
+    orig_max_y INTEGER,
+    orig_plane INTEGER,
     cost INTEGER,
     next_node_type TEXT,
     next_node_id INTEGER,
@@ -310,15 +403,76 @@ SELECT 'item', id, NULL, NULL, NULL,
        dest_min_x, dest_min_y, dest_plane, cost, requirements
 FROM teleports_item_nodes
 UNION ALL
-SELECT 'ifslot', id, NULL, NULL, NULL,
+SELECT 'ifslot', id, orig_min_x, orig_min_y, orig_plane,
        CAST(dest_min_x AS INTEGER), CAST(dest_min_y AS INTEGER),
        CAST(dest_plane AS INTEGER), cost, requirements
 FROM teleports_ifslot_nodes
-WHERE dest_min_x IS NOT NULL;
+WHERE dest_min_x IS NOT NULL AND dest_min_y IS NOT NULL AND dest_plane IS NOT NULL;
 
     "#,
     )?;
 
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES ('schema_version', ?1)",
+        [SCHEMA_VERSION.to_string()],
+    )?;
+
+    Ok(())
+}
+
+fn has_meta_table(conn: &Connection) -> Result<bool> {
+    let has_meta: Option<String> = conn
+        .query_row("SELECT name FROM sqlite_master WHERE type='table' AND name='meta'", [], |row| row.get(0))
+        .optional()?;
+    Ok(has_meta.is_some())
+}
+
+/// Reads one `meta` value; `None` when the key or the whole meta table is absent.
+pub fn read_meta(conn: &Connection, key: &str) -> Result<Option<String>> {
+    if !has_meta_table(conn)? {
+        return Ok(None);
+    }
+    Ok(conn
+        .query_row("SELECT value FROM meta WHERE key=?1", [key], |row| row.get(0))
+        .optional()?)
+}
+
+/// Upserts one `meta` value. Returns false without writing when the DB has no meta table or its
+/// key CHECK predates `key` (the DB was created by an older schema version).
+pub fn write_meta(conn: &Connection, key: &str, value: &str) -> Result<bool> {
+    if !has_meta_table(conn)? {
+        return Ok(false);
+    }
+    match conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", [key, value]) {
+        Ok(_) => Ok(true),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::ConstraintViolation => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reads `meta.schema_version`; `None` for DBs created before the meta table existed.
+pub fn read_schema_version(conn: &Connection) -> Result<Option<i64>> {
+    let value = read_meta(conn, "schema_version")?;
+    match value {
+        Some(v) => Ok(Some(
+            v.trim().parse().with_context(|| format!("Invalid meta.schema_version '{}'", v))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Refuses DBs stamped with a newer schema than this build knows; unversioned DBs are accepted.
+pub fn ensure_supported_schema(conn: &Connection, db_path: &Path) -> Result<()> {
+    if let Some(v) = read_schema_version(conn)? {
+        if v > SCHEMA_VERSION {
+            bail!(
+                "{} has schema_version {}, but this build only understands up to {}; use a newer rs3cache_extractor",
+                db_path.display(),
+                v,
+                SCHEMA_VERSION
+            );
+        }
+    }
     Ok(())
 }
 
@@ -332,3 +486,77 @@ fn schema_has_user_tables(conn: &Connection) -> Result<bool> {
         .optional()?;
     Ok(existing.is_some())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_tables_stamps_the_schema_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        assert_eq!(read_schema_version(&conn).unwrap(), Some(SCHEMA_VERSION));
+        ensure_supported_schema(&conn, Path::new("t.db")).unwrap();
+        assert!(create_tables(&conn).unwrap_err().to_string().contains("already contains tables"));
+    }
+
+    #[test]
+    fn newer_schema_versions_are_rejected_and_unversioned_dbs_accepted() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE tiles (x INTEGER, y INTEGER, plane INTEGER, walk_mask INTEGER);").unwrap();
+        assert_eq!(read_schema_version(&conn).unwrap(), None);
+        ensure_supported_schema(&conn, Path::new("old.db")).unwrap();
+
+        conn.execute_batch("CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);").unwrap();
+        conn.execute("INSERT INTO meta VALUES ('schema_version', ?1)", [(SCHEMA_VERSION + 1).to_string()]).unwrap();
+        let err = ensure_supported_schema(&conn, Path::new("new.db")).unwrap_err().to_string();
+        assert!(err.contains(&format!("new.db has schema_version {}", SCHEMA_VERSION + 1)), "{err}");
+
+        conn.execute("UPDATE meta SET value = 'v2'", []).unwrap();
+        assert!(read_schema_version(&conn).is_err());
+    }
+
+    #[test]
+    fn write_meta_reports_keys_an_older_check_rejects() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(!write_meta(&conn, "teleport_kinds", "door").unwrap());
+        conn.execute_batch(
+            "CREATE TABLE meta (key TEXT PRIMARY KEY CHECK (key IN ('schema_version')), value TEXT NOT NULL);",
+        )
+        .unwrap();
+        assert!(write_meta(&conn, "schema_version", "1").unwrap());
+        assert!(!write_meta(&conn, "teleport_kinds", "door").unwrap());
+        assert_eq!(read_meta(&conn, "teleport_kinds").unwrap(), None);
+    }
+
+    #[test]
+    fn sqlite_modes_set_their_pragmas() {
+        let dir = tempfile::tempdir().unwrap();
+        for (mode, journal, synchronous) in
+            [(SqliteMode::Fast, "wal", 1), (SqliteMode::Safe, "wal", 2), (SqliteMode::Memory, "memory", 0)]
+        {
+            let conn = Connection::open(dir.path().join(format!("{:?}.db", mode))).unwrap();
+            mode.apply(&conn).unwrap();
+            let got: String = conn.query_row("PRAGMA journal_mode", [], |r| r.get(0)).unwrap();
+            assert_eq!(got, journal, "{:?}", mode);
+            let got: i64 = conn.query_row("PRAGMA synchronous", [], |r| r.get(0)).unwrap();
+            assert_eq!(got, synchronous, "{:?}", mode);
+            let temp_store: i64 = conn.query_row("PRAGMA temp_store", [], |r| r.get(0)).unwrap();
+            assert_eq!(temp_store, 2, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn teleports_all_skips_ifslots_without_a_full_destination() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO teleports_ifslot_nodes (id, dest_min_x, dest_min_y, dest_plane) VALUES
+                 (1, 10, 20, 0), (2, 10, 20, NULL), (3, NULL, 20, 0), (4, 10, NULL, 0);",
+        )
+        .unwrap();
+        let mut stmt = conn.prepare("SELECT id FROM teleports_all WHERE kind = 'ifslot'").unwrap();
+        let ids = stmt.query_map([], |r| r.get::<_, i64>(0)).unwrap().collect::<rusqlite::Result<Vec<_>>>().unwrap();
+        assert_eq!(ids, vec![1]);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod db;
 pub mod commands;
+pub mod models;
+pub mod navmesh;
+pub mod reachable_bitmap;
+#[cfg(test)]
+mod test_support;
 pub mod util;
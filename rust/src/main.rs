@@ -1,9 +1,7 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
-mod util;
-mod db;
-mod commands;
+use rs3cache_extractor::{commands, db, util};
 
 #[derive(Parser, Debug)]
 #[command(name = "rs3cache_extractor", version, about = "Tools for RS3 cache extraction")] 
@@ -19,16 +17,37 @@ enum Commands {
         /// Path to JSON folder (defaults to repo_root/out/walk)
         #[arg(long)]
         json_dir: Option<PathBuf>,
+        /// Path to a single tiles JSON file (optionally gzipped, *.json.gz) instead of a folder
+        #[arg(long, conflicts_with = "json_dir")]
+        json_file: Option<PathBuf>,
         /// Path to SQLite DB (defaults to repo_root/tiles.db)
         #[arg(long)]
         db: Option<PathBuf>,
-        #[arg(long, help = "Path to overrides file with lines: x,y,z,walk_mask (comma-separated)")]
+        #[arg(long, help = "Path to overrides file: lines of x,y,z,walk_mask (comma-separated), or a JSON array of tiles")]
         overrides: Option<PathBuf>,
+        /// Overrides file format (default: json for *.json, csv otherwise)
+        #[arg(long, value_enum)]
+        overrides_format: Option<commands::load_tiles::OverridesFormat>,
+        /// SQLite journal/synchronous settings for the bulk load (durability vs. throughput)
+        #[arg(long, value_enum, default_value_t = SqliteModeArg(db::SqliteMode::Fast))]
+        sqlite_mode: SqliteModeArg,
+        /// Fail (and load nothing) if any tile file does not match the expected JSON structure or a tile appears twice
+        #[arg(long)]
+        strict: bool,
+        /// Worker threads for parsing tile files (default: one per core)
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Skip and count individual tiles that fail to parse instead of rejecting their file
+        #[arg(long, conflicts_with = "strict")]
+        skip_bad_tiles: bool,
+        /// Added to every loaded tile's plane, for dumps that number planes differently
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        plane_offset: i64,
     },
 
     /// Import XLSX or Google Sheet into worldReachableTiles.db using the native Rust importer
     ImportXlsx {
-        /// Path to .xlsx file or Google Sheets URL
+        /// Path to .xlsx file, Google Sheets URL, or a .zip (path or URL) containing an .xlsx
         #[arg(long)]
         xlsx: String,
         /// Path to SQLite DB (default: worldReachableTiles.db)
@@ -43,6 +62,21 @@ enum Commands {
         /// Only import the specified sheets/tables
         #[arg(long, num_args = 1..)]
         sheets: Vec<String>,
+        /// After importing, report door rows with non-adjacent inside/outside tiles or no real ids
+        #[arg(long)]
+        validate_doors: bool,
+        /// Spreadsheet entry to import when --xlsx points at a .zip holding several (a .csv entry loads the table named by its file stem)
+        #[arg(long)]
+        zip_entry: Option<String>,
+        /// Retries (with exponential backoff) for HTTP 429/5xx or network errors when downloading
+        #[arg(long, default_value_t = 3)]
+        download_retries: u32,
+        /// Only list each sheet's header-to-column mapping and unmatched sheets/headers; import nothing
+        #[arg(long, conflicts_with = "dry_run")]
+        check_only: bool,
+        /// Added to every plane column of imported teleport rows (match load-tiles --plane-offset)
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        plane_offset: i64,
     },
 
     /// Build worldReachableTiles.db from tiles.db by BFS + teleports
@@ -53,38 +87,262 @@ enum Commands {
         /// Output SQLite DB (default: repo_root/worldReachableTiles.db)
         #[arg(long)]
         out: Option<PathBuf>,
-        /// Start tile X (default: 3200)
+        /// Start tile X (default: the source's meta.reachability_start, else 3200); saved to meta after a successful run, except with --target
+        #[arg(long)]
+        start_x: Option<i32>,
+        /// Start tile Y (default: the source's meta.reachability_start, else 3200); saved to meta after a successful run, except with --target
+        #[arg(long)]
+        start_y: Option<i32>,
+        /// Start plane (default: the source's meta.reachability_start, else 0); saved to meta after a successful run, except with --target
+        #[arg(long)]
+        start_plane: Option<i32>,
+        /// Only check whether this tile (x,y,plane) is reachable, via A*; no output DB is written
+        #[arg(long, value_parser = util::parse_tile_arg)]
+        target: Option<(i32, i32, i32)>,
+        /// Worker threads for writing the cleaned tiles (default: one per core)
+        #[arg(long)]
+        threads: Option<usize>,
+        /// What to do if the output DB already exists
+        #[arg(long, value_enum, default_value_t = commands::tile_cleaner::DestDbExists::Fail)]
+        dest_db_exists: commands::tile_cleaner::DestDbExists,
+        /// Write only the reachable set, as a packed per-region bitmap, to this file; no output DB is written
+        #[arg(long)]
+        emit_bitmap: Option<PathBuf>,
+        /// Only copy teleport rows whose origin and destination tiles are reachable (default: copy all)
+        #[arg(long)]
+        keep_teleports_in_scope: bool,
+        /// Comma-separated teleport kinds the search may use (default: all)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        teleport_kinds: Option<Vec<commands::tile_cleaner::TeleportKind>>,
+        /// Keep only tiles within N steps of the start (default: unbounded)
+        #[arg(long, conflicts_with = "target")]
+        max_depth: Option<u32>,
+        /// Steps a teleport/door hop counts as under --max-depth
+        #[arg(long, default_value_t = 1, requires = "max_depth")]
+        teleport_depth_cost: u32,
+        /// Continue when the source tiles table is empty instead of failing
+        #[arg(long)]
+        allow_empty: bool,
+    },
+
+    /// Merge tiles and teleport tables from one tiles DB into another
+    MergeTiles {
+        /// DB to merge into (modified in place)
+        #[arg(long)]
+        into: PathBuf,
+        /// DB to merge rows from
+        #[arg(long)]
+        from: PathBuf,
+        /// Which DB's row wins when both contain the same primary key
+        #[arg(long, value_enum, default_value_t = commands::merge_tiles::MergePrefer::Other)]
+        prefer: commands::merge_tiles::MergePrefer,
+    },
+
+    /// Per-plane walkable/blocked tile counts bucketed into a grid (CSV or JSON heatmap input)
+    ///
+    /// Columns are chunk_x, chunk_z, walkable, blocked; chunk_z indexes the tile y axis.
+    Density {
+        /// SQLite DB to read (default: repo_root/tiles.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        plane: i32,
+        /// Cell size in tiles (64 = one map region)
+        #[arg(long, default_value_t = 64)]
+        grid: i32,
+        #[arg(long, value_enum, default_value_t = commands::density::DensityFormat::Csv)]
+        format: commands::density::DensityFormat,
+        /// Output file (default: stdout)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Render a plane's walkability as ASCII (small areas) or PNG
+    Render {
+        /// SQLite DB to read (default: repo_root/tiles.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        plane: i32,
+        /// Area bounds (inclusive); unset sides default to the plane's extent
+        #[arg(long)]
+        min_x: Option<i32>,
+        #[arg(long)]
+        max_x: Option<i32>,
+        #[arg(long)]
+        min_y: Option<i32>,
+        #[arg(long)]
+        max_y: Option<i32>,
+        #[arg(long, value_enum, default_value_t = commands::render::RenderFormat::Ascii)]
+        format: commands::render::RenderFormat,
+        /// Output file (required for png; ASCII defaults to stdout)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Generate a synthetic tiles DB for benchmarking/profiling
+    #[command(hide = true)]
+    GenWorld {
+        /// Output SQLite DB (must not already contain tables)
+        #[arg(long)]
+        db: PathBuf,
+        #[arg(long, default_value_t = 256)]
+        width: i32,
+        #[arg(long, default_value_t = 256)]
+        height: i32,
+        #[arg(long, default_value_t = 1)]
+        planes: i32,
+        /// Probability that a tile is walkable (0..1)
+        #[arg(long, default_value_t = 0.7)]
+        density: f64,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of random doors/lodestones to add
+        #[arg(long, default_value_t = 16)]
+        teleports: usize,
+        /// World X of the generated area's south-west corner
         #[arg(long, default_value_t = 3200)]
-        start_x: i32,
-        /// Start tile Y (default: 3200)
+        origin_x: i32,
+        /// World Y of the generated area's south-west corner
         #[arg(long, default_value_t = 3200)]
-        start_y: i32,
-        /// Start plane (default: 0)
-        #[arg(long, default_value_t = 0)]
-        start_plane: i32,
+        origin_y: i32,
     }
 
 
    
 }
 
+/// `--sqlite-mode` values. The names and help come from `db::SqliteMode` so they are written once,
+/// while the DB layer stays free of CLI types.
+#[derive(Clone, Copy, Debug)]
+struct SqliteModeArg(db::SqliteMode);
+
+impl clap::ValueEnum for SqliteModeArg {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            SqliteModeArg(db::SqliteMode::Fast),
+            SqliteModeArg(db::SqliteMode::Safe),
+            SqliteModeArg(db::SqliteMode::Memory),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(clap::builder::PossibleValue::new(self.0.name()).help(self.0.description()))
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::LoadTiles { json_dir, db, overrides } => {
+        Commands::LoadTiles {
+            json_dir,
+            json_file,
+            db,
+            overrides,
+            overrides_format,
+            sqlite_mode,
+            strict,
+            threads,
+            skip_bad_tiles,
+            plane_offset,
+        } => {
             let (def_json, def_db) = util::default_paths();
-            let json_folder = json_dir.unwrap_or(def_json);
+            let json_source = json_file.or(json_dir).unwrap_or(def_json);
             let db_path = db.unwrap_or(def_db);
-            commands::load_tiles::cmd_load_tiles(&json_folder, &db_path, overrides.as_deref())
+            let opts = commands::load_tiles::LoadOptions {
+                overrides_path: overrides.as_deref(),
+                overrides_format,
+                sqlite_mode: sqlite_mode.0,
+                strict,
+                threads,
+                skip_bad_tiles,
+                plane_offset,
+            };
+            db::with_lock_hint(commands::load_tiles::cmd_load_tiles(&json_source, &db_path, &opts), &db_path)
         }
-        Commands::ImportXlsx { xlsx, db, dry_run, truncate, sheets } => {
-            commands::import_xlsx::cmd_import_xlsx(&xlsx, &db, dry_run, &truncate, &sheets)
+        Commands::ImportXlsx {
+            xlsx,
+            db,
+            dry_run,
+            truncate,
+            sheets,
+            validate_doors,
+            zip_entry,
+            download_retries,
+            check_only,
+            plane_offset,
+        } => {
+            let opts = commands::import_xlsx::ImportOptions {
+                dry_run,
+                truncate: &truncate,
+                sheets: &sheets,
+                validate_doors,
+                zip_entry: zip_entry.as_deref(),
+                download_retries,
+                check_only,
+                plane_offset,
+            };
+            db::with_lock_hint(commands::import_xlsx::cmd_import_xlsx(&xlsx, &db, &opts), &db)
         }
-        Commands::TileCleaner { src, out, start_x, start_y, start_plane } => {
+        Commands::TileCleaner {
+            src,
+            out,
+            start_x,
+            start_y,
+            start_plane,
+            target,
+            threads,
+            dest_db_exists,
+            emit_bitmap,
+            keep_teleports_in_scope,
+            teleport_kinds,
+            max_depth,
+            teleport_depth_cost,
+            allow_empty,
+        } => {
             let root = util::repo_root();
             let src_path = src.unwrap_or(root.join("tiles.db"));
             let out_path = out.unwrap_or(root.join("worldReachableTiles.db"));
-            commands::tile_cleaner::cmd_tile_cleaner(&src_path, &out_path, start_x, start_y, start_plane)
+            let opts = commands::tile_cleaner::CleanerOptions {
+                start_x,
+                start_y,
+                start_plane,
+                target,
+                threads,
+                dest_db_exists,
+                emit_bitmap: emit_bitmap.as_deref(),
+                keep_teleports_in_scope,
+                teleport_kinds: teleport_kinds.as_deref().unwrap_or(commands::tile_cleaner::TeleportKind::ALL),
+                max_depth,
+                teleport_depth_cost,
+                allow_empty,
+            };
+            commands::tile_cleaner::cmd_tile_cleaner(&src_path, &out_path, &opts)
+        }
+        Commands::MergeTiles { into, from, prefer } => {
+            db::with_lock_hints(commands::merge_tiles::cmd_merge_tiles(&into, &from, prefer), &[&into, &from])
+        }
+        Commands::Density { db, plane, grid, format, out } => {
+            let db_path = db.unwrap_or(util::default_paths().1);
+            commands::density::cmd_density(&db_path, plane, grid, format, out.as_deref())
+        }
+        Commands::Render { db, plane, min_x, max_x, min_y, max_y, format, out } => {
+            let db_path = db.unwrap_or(util::default_paths().1);
+            let bounds = commands::render::RenderBounds { min_x, max_x, min_y, max_y };
+            commands::render::cmd_render(&db_path, plane, bounds, format, out.as_deref())
+        }
+        Commands::GenWorld { db, width, height, planes, density, seed, teleports, origin_x, origin_y } => {
+            let opts = commands::gen_world::GenWorldOptions {
+                width,
+                height,
+                planes,
+                density,
+                seed,
+                teleports,
+                origin_x,
+                origin_y,
+            };
+            commands::gen_world::cmd_gen_world(&db, &opts)
         }
         }
 }
@@ -0,0 +1,79 @@
+/// A tile coordinate packed into a single `u64` for cheap hashing in visited sets.
+///
+/// Bit layout (most significant first):
+/// - bits 56..63: unused (always 0)
+/// - bits 48..55: plane (0..=255)
+/// - bits 24..47: x (0..=16_777_215)
+/// - bits 0..23:  y (0..=16_777_215)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileId(pub u64);
+
+impl TileId {
+    pub const COORD_BITS: u32 = 24;
+    pub const PLANE_BITS: u32 = 8;
+    pub const MAX_COORD: i32 = (1 << Self::COORD_BITS) - 1;
+    pub const MAX_PLANE: i32 = (1 << Self::PLANE_BITS) - 1;
+
+    const COORD_MASK: u64 = (1 << Self::COORD_BITS) - 1;
+    const PLANE_MASK: u64 = (1 << Self::PLANE_BITS) - 1;
+
+    /// Packs a coordinate, returning `None` when any component is outside the encodable range.
+    pub fn pack(x: i32, y: i32, plane: i32) -> Option<TileId> {
+        if !(0..=Self::MAX_COORD).contains(&x)
+            || !(0..=Self::MAX_COORD).contains(&y)
+            || !(0..=Self::MAX_PLANE).contains(&plane)
+        {
+            return None;
+        }
+        let packed = ((plane as u64) << (2 * Self::COORD_BITS))
+            | ((x as u64) << Self::COORD_BITS)
+            | (y as u64);
+        Some(TileId(packed))
+    }
+
+    pub fn unpack(self) -> (i32, i32, i32) {
+        let y = (self.0 & Self::COORD_MASK) as i32;
+        let x = ((self.0 >> Self::COORD_BITS) & Self::COORD_MASK) as i32;
+        let plane = ((self.0 >> (2 * Self::COORD_BITS)) & Self::PLANE_MASK) as i32;
+        (x, y, plane)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn round_trips_the_range_edges() {
+        for (x, y, plane) in [(0, 0, 0), (TileId::MAX_COORD, TileId::MAX_COORD, TileId::MAX_PLANE), (3200, 3200, 3)] {
+            assert_eq!(TileId::pack(x, y, plane).unwrap().unpack(), (x, y, plane));
+        }
+        assert_eq!(TileId::pack(1, 2, 3).unwrap().0, (3 << 48) | (1 << 24) | 2);
+    }
+
+    #[test]
+    fn rejects_out_of_range_components() {
+        assert!(TileId::pack(-1, 0, 0).is_none());
+        assert!(TileId::pack(0, -1, 0).is_none());
+        assert!(TileId::pack(0, 0, -1).is_none());
+        assert!(TileId::pack(TileId::MAX_COORD + 1, 0, 0).is_none());
+        assert!(TileId::pack(0, TileId::MAX_COORD + 1, 0).is_none());
+        assert!(TileId::pack(0, 0, TileId::MAX_PLANE + 1).is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn pack_unpack_round_trips(x in 0..=TileId::MAX_COORD, y in 0..=TileId::MAX_COORD, plane in 0..=TileId::MAX_PLANE) {
+            let id = TileId::pack(x, y, plane).unwrap();
+            prop_assert_eq!(id.unpack(), (x, y, plane));
+            prop_assert_eq!(id.0 >> 56, 0);
+        }
+
+        #[test]
+        fn distinct_tiles_pack_distinctly(a in (0..=TileId::MAX_COORD, 0..=TileId::MAX_COORD, 0..=TileId::MAX_PLANE),
+                                          b in (0..=TileId::MAX_COORD, 0..=TileId::MAX_COORD, 0..=TileId::MAX_PLANE)) {
+            prop_assert_eq!(a == b, TileId::pack(a.0, a.1, a.2) == TileId::pack(b.0, b.1, b.2));
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Tile walkability as seen by the reachability search: raw `walk_mask` decoding, optional
+//! overrides, and reconciliation (reciprocal cardinals, diagonals only past open corners).
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+
+type Tile = (i32, i32, i32);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct WalkMaskOverride {
+    pub(crate) force_mask: Option<i64>,
+    pub(crate) or_mask: i64,
+}
+
+const RECIP: &[(&str, &str)] = &[
+    ("left", "right"),
+    ("right", "left"),
+    ("top", "bottom"),
+    ("bottom", "top"),
+    ("topleft", "bottomright"),
+    ("topright", "bottomleft"),
+    ("bottomleft", "topright"),
+    ("bottomright", "topleft"),
+];
+
+pub(crate) fn key_delta(k: &str) -> Option<(i32, i32, i32)> {
+    match k {
+        "top" => Some((0, 1, 0)),
+        "bottom" => Some((0, -1, 0)),
+        "right" => Some((1, 0, 0)),
+        "left" => Some((-1, 0, 0)),
+        "topright" => Some((1, 1, 0)),
+        "topleft" => Some((-1, 1, 0)),
+        "bottomright" => Some((1, -1, 0)),
+        "bottomleft" => Some((-1, -1, 0)),
+        _ => None,
+    }
+}
+
+/// Maps a direction name as written by any cache extractor (any casing, compass names, `_`/`-`
+/// separators, abbreviations) to the walk_mask name used throughout this crate, e.g.
+/// `"NorthEast"` -> `"topright"`.
+pub fn canonical_direction(name: &str) -> Option<&'static str> {
+    let key: String = name
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    match key.as_str() {
+        "left" | "west" | "w" => Some("left"),
+        "right" | "east" | "e" => Some("right"),
+        "top" | "north" | "n" | "up" => Some("top"),
+        "bottom" | "south" | "s" | "down" => Some("bottom"),
+        "topleft" | "northwest" | "nw" | "upleft" => Some("topleft"),
+        "topright" | "northeast" | "ne" | "upright" => Some("topright"),
+        "bottomleft" | "southwest" | "sw" | "downleft" => Some("bottomleft"),
+        "bottomright" | "southeast" | "se" | "downright" => Some("bottomright"),
+        _ => None,
+    }
+}
+
+/// Encodes a direction -> allowed map with arbitrary direction spellings into walk_mask bits.
+pub fn walk_data_to_mask(data: &HashMap<String, bool>) -> Result<i64> {
+    let mut canonical: HashMap<String, bool> = HashMap::new();
+    for (k, v) in data {
+        let Some(dir) = canonical_direction(k) else {
+            bail!("unknown walk direction '{}'", k);
+        };
+        // A direction spelled twice is allowed if any spelling allows it.
+        *canonical.entry(dir.to_string()).or_insert(false) |= *v;
+    }
+    Ok(WalkCache::encode_mask(&canonical))
+}
+
+fn diag_require(k: &str) -> Option<(&'static str, &'static str)> {
+    match k {
+        "topleft" => Some(("top", "left")),
+        "topright" => Some(("top", "right")),
+        "bottomleft" => Some(("bottom", "left")),
+        "bottomright" => Some(("bottom", "right")),
+        _ => None,
+    }
+}
+
+fn dir_to_bit(dir: &str) -> Option<i64> {
+    match dir {
+        "left" => Some(1 << 0),
+        "bottom" => Some(1 << 1),
+        "right" => Some(1 << 2),
+        "top" => Some(1 << 3),
+        "topleft" => Some(1 << 4),
+        "bottomleft" => Some(1 << 5),
+        "bottomright" => Some(1 << 6),
+        "topright" => Some(1 << 7),
+        _ => None,
+    }
+}
+
+/// Walk-mask overrides that open fairy ring tiles fully and let each neighbour step onto the ring,
+/// as the game does regardless of the cached masks. Empty when the DB has no fairy ring table.
+pub(crate) fn build_fairy_ring_overrides(conn: &Connection) -> Result<std::sync::Arc<HashMap<Tile, WalkMaskOverride>>> {
+    let mut out: HashMap<Tile, WalkMaskOverride> = HashMap::new();
+
+    let mut stmt = match conn.prepare("SELECT x, y, plane FROM teleports_fairy_rings_nodes") {
+        Ok(s) => s,
+        Err(_) => return Ok(std::sync::Arc::new(out)),
+    };
+
+    let mut rows = stmt.query([])?;
+    while let Some(r) = rows.next()? {
+        let x: Option<i64> = r.get(0)?;
+        let y: Option<i64> = r.get(1)?;
+        let p: Option<i64> = r.get(2)?;
+        let (Some(x), Some(y), Some(p)) = (x, y, p) else { continue; };
+        let ring: Tile = (x as i32, y as i32, p as i32);
+
+        out.entry(ring)
+            .and_modify(|e| e.force_mask = Some(255))
+            .or_insert(WalkMaskOverride { force_mask: Some(255), or_mask: 0 });
+
+        let neighbors: [((i32, i32), &str); 8] = [
+            ((-1, 0), "right"),
+            ((1, 0), "left"),
+            ((0, -1), "top"),
+            ((0, 1), "bottom"),
+            ((-1, -1), "topright"),
+            ((1, -1), "topleft"),
+            ((-1, 1), "bottomright"),
+            ((1, 1), "bottomleft"),
+        ];
+
+        for &((dx, dy), dir) in &neighbors {
+            let nt: Tile = (ring.0 + dx, ring.1 + dy, ring.2);
+            let mut bits = dir_to_bit(dir).unwrap_or(0);
+            if let Some((r1, r2)) = diag_require(dir) {
+                bits |= dir_to_bit(r1).unwrap_or(0) | dir_to_bit(r2).unwrap_or(0);
+            }
+            out.entry(nt)
+                .and_modify(|e| {
+                    if e.force_mask.is_none() {
+                        e.or_mask |= bits;
+                    }
+                })
+                .or_insert(WalkMaskOverride { force_mask: None, or_mask: bits });
+        }
+    }
+
+    Ok(std::sync::Arc::new(out))
+}
+
+pub(crate) struct WalkCache {
+    raw: HashMap<Tile, HashMap<String, bool>>, 
+    reconciled: HashMap<Tile, HashMap<String, bool>>, 
+    overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>,
+}
+
+impl WalkCache {
+    pub(crate) fn new_with_overrides(overrides: std::sync::Arc<HashMap<Tile, WalkMaskOverride>>) -> Self {
+        Self {
+            raw: HashMap::new(),
+            reconciled: HashMap::new(),
+            overrides,
+        }
+    }
+
+    // Mapping order for walk_mask bits: 0..7
+    // [left, bottom, right, top, topleft, bottomleft, bottomright, topright]
+    fn mask_dirs() -> [&'static str; 8] {
+        [
+            "left",
+            "bottom",
+            "right",
+            "top",
+            "topleft",
+            "bottomleft",
+            "bottomright",
+            "topright",
+        ]
+    }
+
+    fn decode_mask(mask: i64) -> HashMap<String, bool> {
+        let mut out = HashMap::new();
+        let dirs = Self::mask_dirs();
+        for i in 0..8 {
+            if (mask & (1 << i)) != 0 {
+                out.insert(dirs[i].to_string(), true);
+            }
+        }
+        out
+    }
+
+    pub(crate) fn encode_mask(map: &HashMap<String, bool>) -> i64 {
+        let mut mask: i64 = 0;
+        let dirs = Self::mask_dirs();
+        for i in 0..8 {
+            if map.get(dirs[i]).copied().unwrap_or(false) { mask |= 1 << i; }
+        }
+        mask
+    }
+
+    fn get_raw(&mut self, conn: &Connection, t: Tile) -> Result<HashMap<String, bool>> {
+        if let Some(m) = self.raw.get(&t) { return Ok(m.clone()); }
+        let (x, y, p) = t;
+        let row: Option<Option<i64>> = conn
+            .query_row(
+                "SELECT walk_mask FROM tiles WHERE x=?1 AND y=?2 AND plane=?3",
+                params![x, y, p],
+                |row| Ok(row.get(0)?),
+            )
+            .optional()?;
+
+        let Some(walk_mask) = row else {
+            // Tile row does not exist; never allow overrides to "create" walkability for missing tiles.
+            self.raw.insert(t, HashMap::new());
+            return Ok(HashMap::new());
+        };
+
+        let mut w = walk_mask.unwrap_or(0);
+        if let Some(ov) = self.overrides.get(&t) {
+            if let Some(f) = ov.force_mask {
+                w = f;
+            } else {
+                w |= ov.or_mask;
+            }
+        }
+
+        let m = if w != 0 { Self::decode_mask(w) } else { HashMap::new() };
+        self.raw.insert(t, m.clone());
+        Ok(m)
+    }
+
+    pub(crate) fn get_reconciled(&mut self, conn: &Connection, t: Tile) -> Result<HashMap<String, bool>> {
+        if let Some(m) = self.reconciled.get(&t) { return Ok(m.clone()); }
+        let mut base = self.get_raw(conn, t)?;
+        if base.is_empty() {
+            self.reconciled.insert(t, HashMap::new());
+            return Ok(HashMap::new());
+        }
+        let (tx, ty, tp) = t;
+        
+        for key in ["left", "right", "top", "bottom"] {
+            if !base.get(key).copied().unwrap_or(false) { continue; }
+            if let Some((dx, dy, dp)) = key_delta(key) {
+                let n = (tx + dx, ty + dy, tp + dp);
+                let nwalk = self.get_raw(conn, n)?;
+                let recip = RECIP.iter().find(|(a, _)| *a == key).map(|(_, b)| *b).unwrap();
+                let nrecip = nwalk.get(recip).copied().unwrap_or(false);
+                if !nrecip { base.insert(key.to_string(), false); }
+            }
+        }
+        for key in ["topleft", "topright", "bottomleft", "bottomright"] {
+            if !base.get(key).copied().unwrap_or(false) { continue; }
+            if let Some((r1, r2)) = diag_require(key) {
+                if !(base.get(r1).copied().unwrap_or(false) && base.get(r2).copied().unwrap_or(false)) {
+                    base.insert(key.to_string(), false);
+                    continue;
+                }
+            }
+            if let Some((dx, dy, dp)) = key_delta(key) {
+                let n = (tx + dx, ty + dy, tp + dp);
+                let nwalk = self.get_raw(conn, n)?;
+                let recip = RECIP.iter().find(|(a, _)| *a == key).map(|(_, b)| *b).unwrap();
+                let nrecip = nwalk.get(recip).copied().unwrap_or(false);
+                if !nrecip { base.insert(key.to_string(), false); }
+            }
+        }
+        self.reconciled.insert(t, base.clone());
+        Ok(base)
+    }
+}
+
+pub(crate) fn neighbors_from_reconciled(map: &HashMap<String, bool>, t: Tile) -> Vec<Tile> {
+    let (x, y, p) = t;
+    let mut out = Vec::new();
+    for (k, allowed) in map.iter() {
+        if !*allowed { continue; }
+        if let Some((dx, dy, dp)) = key_delta(k) {
+            out.push((x + dx, y + dy, p + dp));
+        }
+    }
+    out
+}
+
+/// Read-only walkability queries against a tiles DB, using the same reconciliation and fairy ring
+/// overrides as `tile-cleaner`. Results are cached per tile, so repeated queries in one area are cheap.
+///
+/// ```
+/// use rs3cache_extractor::{db, navmesh::TileQuery};
+/// # fn main() -> anyhow::Result<()> {
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("tiles.db");
+/// let conn = rusqlite::Connection::open(&path)?;
+/// db::create_tables(&conn)?;
+/// // (3200, 3200) is open all round; its east neighbour only opens back west; nothing lies west.
+/// conn.execute_batch(
+///     "INSERT INTO tiles (x, y, plane, walk_mask) VALUES
+///          (3200, 3200, 0, 255), (3201, 3200, 0, 1), (3200, 3201, 0, 255), (3200, 3199, 0, 0);",
+/// )?;
+/// drop(conn);
+///
+/// let mut q = TileQuery::open(&path)?;
+/// assert!(q.is_walkable(3200, 3200, 0)?);
+/// assert!(!q.is_walkable(3200, 3199, 0)?);
+/// assert!(!q.is_walkable(3199, 3200, 0)?);
+/// assert!(q.can_cross(3200, 3200, 0, "east")?);
+/// assert!(q.can_cross(3200, 3200, 0, "north")?);
+/// assert!(!q.can_cross(3200, 3200, 0, "west")?);
+/// assert!(!q.can_cross(3200, 3200, 0, "south")?);
+/// assert!(!q.can_cross(3201, 3200, 0, "north")?);
+/// // Only right (0x04) and top (0x08) survive reconciliation.
+/// assert_eq!(q.reconciled_walk(3200, 3200, 0)?, 0x0c);
+/// assert_eq!(q.reconciled_walk(3199, 3200, 0)?, 0);
+/// assert!(q.can_cross(3200, 3200, 0, "sideways").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct TileQuery {
+    conn: Connection,
+    cache: WalkCache,
+}
+
+impl TileQuery {
+    /// Opens the DB read-only and loads its fairy ring overrides.
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open DB at {}", db_path.display()))?;
+        let overrides = build_fairy_ring_overrides(&conn)?;
+        Ok(Self { conn, cache: WalkCache::new_with_overrides(overrides) })
+    }
+
+    /// True if the tile exists and its stored walk_mask allows any movement.
+    pub fn is_walkable(&mut self, x: i32, y: i32, plane: i32) -> Result<bool> {
+        Ok(!self.cache.get_raw(&self.conn, (x, y, plane))?.is_empty())
+    }
+
+    /// True if the reconciled mask allows stepping from the tile in `dir`: a walk_mask direction
+    /// name (`left`, `bottom`, `right`, `top`, `topleft`, `bottomleft`, `bottomright`, `topright`)
+    /// or any synonym accepted by `canonical_direction`.
+    pub fn can_cross(&mut self, x: i32, y: i32, plane: i32, dir: &str) -> Result<bool> {
+        let Some(dir) = canonical_direction(dir) else {
+            bail!("Unknown walk direction '{}'", dir);
+        };
+        let rec = self.cache.get_reconciled(&self.conn, (x, y, plane))?;
+        Ok(rec.get(dir).copied().unwrap_or(false))
+    }
+
+    /// The tile's walk_mask after reconciliation with its neighbours (0 for missing tiles).
+    pub fn reconciled_walk(&mut self, x: i32, y: i32, plane: i32) -> Result<i64> {
+        let rec = self.cache.get_reconciled(&self.conn, (x, y, plane))?;
+        Ok(WalkCache::encode_mask(&rec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch, tiles_db, OPEN};
+
+    #[test]
+    fn tile_query_applies_fairy_ring_overrides() {
+        let (_dir, path) = scratch("tiles.db");
+        let conn = tiles_db(&path, &[(0, 0, 0, Some(OPEN)), (1, 0, 0, Some(OPEN)), (2, 0, 0, Some(0))]);
+        conn.execute("INSERT INTO teleports_fairy_rings_nodes (id, x, y, plane) VALUES (1, 2, 0, 0)", [])
+            .unwrap();
+        drop(conn);
+
+        let mut q = TileQuery::open(&path).unwrap();
+        assert!(q.is_walkable(2, 0, 0).unwrap());
+        assert!(q.can_cross(1, 0, 0, "East").unwrap());
+        assert!(q.can_cross(2, 0, 0, "left").unwrap());
+    }
+
+    #[test]
+    fn null_walk_mask_is_blocked() {
+        let (_dir, path) = scratch("tiles.db");
+        drop(tiles_db(&path, &[(0, 0, 0, Some(OPEN)), (1, 0, 0, None)]));
+
+        let mut q = TileQuery::open(&path).unwrap();
+        assert!(!q.is_walkable(1, 0, 0).unwrap());
+        assert_eq!(q.reconciled_walk(1, 0, 0).unwrap(), 0);
+        assert!(!q.can_cross(0, 0, 0, "right").unwrap());
+    }
+
+    #[test]
+    fn walk_data_accepts_compass_spellings() {
+        let data = |pairs: &[(&str, bool)]| -> HashMap<String, bool> {
+            pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+        };
+        assert_eq!(walk_data_to_mask(&data(&[("NorthEast", true)])).unwrap(), 1 << 7);
+        assert_eq!(walk_data_to_mask(&data(&[("south_west", true), ("W", true)])).unwrap(), (1 << 5) | 1);
+        // A direction spelled twice is open if either spelling allows it.
+        assert_eq!(walk_data_to_mask(&data(&[("top", false), ("north", true)])).unwrap(), 1 << 3);
+        assert!(walk_data_to_mask(&data(&[("upwards", true)])).is_err());
+    }
+
+    #[test]
+    fn tile_query_reconciles_one_way_edges() {
+        let (_dir, path) = scratch("tiles.db");
+        // (1,0) does not allow stepping back left, so (0,0) may not step right either.
+        let left_bit = 1 << 0;
+        drop(tiles_db(&path, &[(0, 0, 0, Some(OPEN)), (1, 0, 0, Some(OPEN & !left_bit))]));
+
+        let mut q = TileQuery::open(&path).unwrap();
+        assert!(q.is_walkable(0, 0, 0).unwrap());
+        assert!(!q.can_cross(0, 0, 0, "right").unwrap());
+        assert!(!q.is_walkable(5, 5, 0).unwrap());
+        assert_eq!(q.reconciled_walk(5, 5, 0).unwrap(), 0);
+        assert!(q.can_cross(0, 0, 0, "sideways").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Compact on-disk form of a reachable tile set, for overlays that do not need the full DB.
+//!
+//! Layout (little endian):
+//! - magic `RSRB`, format version `u8` (1)
+//! - `u32` number of region blocks
+//! - per block, sorted by (plane, region_x, region_y): `u8` plane, `u32` region_x (x >> 6),
+//!   `u32` region_y (y >> 6), then a 4096-bit bitmap (512 bytes) where bit `(y & 63) * 64 + (x & 63)`
+//!   marks a reachable tile.
+
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::models::TileId;
+
+const MAGIC: &[u8; 4] = b"RSRB";
+const VERSION: u8 = 1;
+const REGION_BITS: i32 = 6;
+const REGION_SIZE: i32 = 1 << REGION_BITS;
+const BLOCK_BYTES: usize = (REGION_SIZE * REGION_SIZE / 8) as usize;
+
+type RegionKey = (u8, u32, u32);
+
+/// Writes `tiles` (packed `TileId` values) as per-region bitmaps; returns the number of regions.
+pub fn write_reachable_bitmap<'a>(path: &Path, tiles: impl IntoIterator<Item = &'a u64>) -> Result<usize> {
+    let mut blocks: BTreeMap<RegionKey, Vec<u8>> = BTreeMap::new();
+    for &id in tiles {
+        let (x, y, plane) = TileId(id).unpack();
+        let key = (plane as u8, (x >> REGION_BITS) as u32, (y >> REGION_BITS) as u32);
+        let bit = ((y & (REGION_SIZE - 1)) * REGION_SIZE + (x & (REGION_SIZE - 1))) as usize;
+        blocks.entry(key).or_insert_with(|| vec![0u8; BLOCK_BYTES])[bit / 8] |= 1 << (bit % 8);
+    }
+
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut w = BufWriter::new(file);
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])?;
+    w.write_all(&(blocks.len() as u32).to_le_bytes())?;
+    for ((plane, rx, ry), bits) in &blocks {
+        w.write_all(&[*plane])?;
+        w.write_all(&rx.to_le_bytes())?;
+        w.write_all(&ry.to_le_bytes())?;
+        w.write_all(bits)?;
+    }
+    w.flush()?;
+    Ok(blocks.len())
+}
+
+/// Reads a file written by `write_reachable_bitmap` (e.g. via `tile-cleaner --emit-bitmap`).
+pub fn load_reachable_bitmap(path: &Path) -> Result<HashSet<TileId>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut r = BufReader::new(file);
+
+    let mut header = [0u8; 9];
+    r.read_exact(&mut header)
+        .with_context(|| format!("{} is too short to be a reachable bitmap", path.display()))?;
+    if &header[..4] != MAGIC {
+        bail!("{} is not a reachable bitmap (bad magic)", path.display());
+    }
+    if header[4] != VERSION {
+        bail!("{} has unsupported bitmap version {}", path.display(), header[4]);
+    }
+    let count = u32::from_le_bytes(header[5..9].try_into().unwrap());
+
+    let mut out = HashSet::new();
+    let mut block_header = [0u8; 9];
+    let mut bits = vec![0u8; BLOCK_BYTES];
+    for i in 0..count {
+        r.read_exact(&mut block_header)
+            .and_then(|_| r.read_exact(&mut bits))
+            .with_context(|| format!("{} is truncated at region block {}", path.display(), i))?;
+        let plane = block_header[0] as i32;
+        let rx = u32::from_le_bytes(block_header[1..5].try_into().unwrap()) as i32;
+        let ry = u32::from_le_bytes(block_header[5..9].try_into().unwrap()) as i32;
+        for (byte_idx, &byte) in bits.iter().enumerate() {
+            if byte == 0 {
+                continue;
+            }
+            for b in 0..8 {
+                if byte & (1 << b) == 0 {
+                    continue;
+                }
+                let bit = (byte_idx * 8 + b) as i32;
+                let x = (rx << REGION_BITS) + bit % REGION_SIZE;
+                let y = (ry << REGION_BITS) + bit / REGION_SIZE;
+                let Some(id) = TileId::pack(x, y, plane) else {
+                    bail!("{} holds out-of-range tile ({}, {}, {})", path.display(), x, y, plane);
+                };
+                out.insert(id);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u64> {
+        [(0, 0, 0), (63, 63, 0), (64, 0, 0), (3200, 3201, 1), (TileId::MAX_COORD, TileId::MAX_COORD, 255)]
+            .iter()
+            .map(|&(x, y, p)| TileId::pack(x, y, p).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_tiles_across_regions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reach.bin");
+        let tiles = sample();
+        assert_eq!(write_reachable_bitmap(&path, &tiles).unwrap(), 4);
+        let loaded = load_reachable_bitmap(&path).unwrap();
+        assert_eq!(loaded, tiles.iter().map(|&id| TileId(id)).collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reach.bin");
+        write_reachable_bitmap(&path, &sample()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let err = load_reachable_bitmap(&path).unwrap_err().to_string();
+        assert!(err.contains("truncated at region block 3"), "{err}");
+
+        std::fs::write(&path, &bytes[..5]).unwrap();
+        assert!(load_reachable_bitmap(&path).unwrap_err().to_string().contains("too short"));
+
+        let mut foreign = bytes.clone();
+        foreign[..4].copy_from_slice(b"PK\x03\x04");
+        std::fs::write(&path, &foreign).unwrap();
+        assert!(load_reachable_bitmap(&path).unwrap_err().to_string().contains("bad magic"));
+
+        let mut newer = bytes;
+        newer[4] = VERSION + 1;
+        std::fs::write(&path, &newer).unwrap();
+        assert!(load_reachable_bitmap(&path).unwrap_err().to_string().contains("unsupported bitmap version"));
+    }
+}
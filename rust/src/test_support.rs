@@ -0,0 +1,31 @@
+//! Small fixture DBs shared by the unit tests.
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+
+/// Every walk_mask direction open.
+pub(crate) const OPEN: i64 = 0xff;
+
+/// Creates a current-schema DB at `path` holding `tiles` as (x, y, plane, walk_mask).
+pub(crate) fn tiles_db(path: &Path, tiles: &[(i32, i32, i32, Option<i64>)]) -> Connection {
+    let conn = Connection::open(path).unwrap();
+    crate::db::create_tables(&conn).unwrap();
+    insert_tiles(&conn, tiles);
+    conn
+}
+
+pub(crate) fn insert_tiles(conn: &Connection, tiles: &[(i32, i32, i32, Option<i64>)]) {
+    let mut stmt = conn
+        .prepare("INSERT INTO tiles (x, y, plane, walk_mask) VALUES (?1, ?2, ?3, ?4)")
+        .unwrap();
+    for &(x, y, plane, mask) in tiles {
+        stmt.execute(params![x, y, plane, mask]).unwrap();
+    }
+}
+
+/// A fresh scratch directory plus a path inside it.
+pub(crate) fn scratch(name: &str) -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    (dir, path)
+}
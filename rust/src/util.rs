@@ -12,3 +12,50 @@ pub fn default_paths() -> (PathBuf, PathBuf) {
     let root = repo_root();
     (root.join(JSON_REL_PATH), root.join(DB_FILE))
 }
+
+/// Parses an `x,y,plane` tile coordinate from the command line.
+pub fn parse_tile_arg(s: &str) -> Result<(i32, i32, i32), String> {
+    let parts: Vec<&str> = s.split(',').map(|p| p.trim()).collect();
+    if parts.len() != 3 {
+        return Err(format!("expected x,y,plane but got '{}'", s));
+    }
+    let parse = |p: &str| {
+        p.parse::<i32>()
+            .map_err(|e| format!("invalid coordinate '{}': {}", p, e))
+    };
+    Ok((parse(parts[0])?, parse(parts[1])?, parse(parts[2])?))
+}
+
+/// Builds a rayon pool for one command run so `--threads` is honoured per invocation instead of
+/// touching rayon's process-wide pool. `None` (or 0) uses one thread per core.
+pub fn build_thread_pool(threads: Option<usize>) -> anyhow::Result<std::sync::Arc<rayon::ThreadPool>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build thread pool: {}", e))?;
+    Ok(std::sync::Arc::new(pool))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tile_args() {
+        assert_eq!(parse_tile_arg("3200, 3201 ,1"), Ok((3200, 3201, 1)));
+        assert!(parse_tile_arg("3200,3201").is_err());
+        assert!(parse_tile_arg("3200,x,0").unwrap_err().contains("invalid coordinate 'x'"));
+    }
+
+    #[test]
+    fn thread_pool_is_scoped_to_the_command() {
+        let pool = build_thread_pool(Some(3)).unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+        assert_eq!(pool.install(rayon::current_num_threads), 3);
+        assert!(build_thread_pool(None).unwrap().current_num_threads() >= 1);
+        assert_eq!(
+            build_thread_pool(Some(0)).unwrap().current_num_threads(),
+            build_thread_pool(None).unwrap().current_num_threads()
+        );
+    }
+}